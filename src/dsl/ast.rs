@@ -3,12 +3,27 @@ pub struct Program {
     pub sim_name: String,
     pub joints: Vec<JointDecl>,
     pub links: Vec<LinkDecl>,
+    pub bodies: Vec<BodyDecl>,
+    pub capsules: Vec<CapsuleDecl>,
+    pub masses: Vec<MassDecl>,
+    pub forces: Vec<ForceDecl>,
     pub constraints: Vec<ConstraintDecl>,
 }
 #[derive(Debug)]
 pub struct JointDecl {
     pub name: String,
     pub position: [f32; 3],
+    pub joint_type: Option<JointTypeDecl>,
+}
+#[derive(Debug)]
+pub enum JointTypeDecl {
+    Fixed,
+    Revolute { axis: Vec3, limits: Option<(f32, f32)> },
+    Slider { axis: Vec3, limits: Option<(f32, f32)> },
+    Spherical { cone: Option<f32> },
+    Universal { axis_a: Vec3, axis_b: Vec3, limit: Option<f32> },
+    Cylindrical { axis: Vec3, limits: Option<(f32, f32)> },
+    Planar { normal: Vec3 },
 }
 #[derive(Debug)]
 pub struct LinkDecl {
@@ -17,25 +32,92 @@ pub struct LinkDecl {
     pub joint_b: String,
 }
 #[derive(Debug)]
+pub struct CapsuleDecl {
+    pub link: String,
+    pub radius: f32,
+    pub layer: u32,
+}
+#[derive(Debug)]
+pub struct MassDecl {
+    pub joints: Vec<String>,
+    pub mass: f32,
+}
+#[derive(Debug)]
+pub enum ForceDecl {
+    Spring { a: String, b: String, rest_length: f32, stiffness: f32, damping: f32 },
+    TorsionSpring { joint_a: String, pivot: String, joint_b: String, rest_angle: f32, stiffness: f32, damping: f32, axis: Vec3 },
+    Gravity { acceleration: Vec3 },
+    Load { joint: String, force: Vec3 },
+    Torque { pivot: String, arm: String, torque: f32, axis: Vec3 },
+}
+#[derive(Debug)]
+pub struct BodyDecl {
+    pub name: String,
+    pub joints: Vec<String>,
+    pub shape_matching: bool,
+}
+#[derive(Debug)]
 pub enum ConstraintDecl {
     Distance { a: String, b: String, value: f32 },
+    DistanceRange { a: String, b: String, min: f32, max: f32 },
     Fixed { joints: Vec<String> },
     Plane { joints: Vec<String>, normal: Vec3, point: Option<Vec3> },
-    PrismaticVector { joints: Vec<String>, axis: Vec3, origin: Vec3 },
+    PrismaticVector { joints: Vec<String>, axis: Vec3, origin: Vec3, limits: Option<(f32, f32)> },
     PrismaticLink { joints: Vec<String>, link: String, origin: Vec3 },
-    FixedAngle { joint_a: String, pivot: String, joint_c: String, angle: f32,},
-    Revolute { joint_a: String, joint_b: String, axis: Vec3, min_angle: f32, max_angle: f32 },
+    FixedAngle { joint_a: String, pivot: String, joint_c: String, angle: f32, keyframes: Option<Vec<(f32, f32)>>, axis: Vec3 },
+    Revolute { joint_a: String, joint_b: String, axis: Vec3, min_angle: f32, max_angle: f32, link: Option<String> },
+    Drive { pivot: String, moving: String, profile: ProfileDecl, axis: Vec3 },
+    Actuator { joint: String, axis: Vec3, profile: ProfileDecl },
+    Gear { pivot_a: String, arm_a: String, pivot_b: String, arm_b: String, ratio: f32, axis: Vec3 },
+    Cam { pivot: String, arm: String, follower: String, profile: CamProfileDecl, roller_radius: f32, axis: Vec3 },
+    RackPinion { pivot: String, arm: String, rack: String, pitch_radius: f32, direction: Vec3, axis: Vec3 },
+    Screw { pivot: String, arm: String, nut: String, lead: f32, axis: Vec3 },
+    Belt { pulleys: Vec<(String, String, f32)>, crossed: bool, axis: Vec3 }, // (pivot, arm, radius)
+    OnCurve { joint: String, curve: CurveDecl, link: Option<String>, axis: Vec3 },
+    Slot { pin: String, link: String, points: Vec<(f32, f32)>, axis: Vec3 },
+    Soft { constraint: Box<ConstraintDecl>, compliance: f32 },
     }
+#[derive(Debug)]
+pub enum ProfileDecl {
+    Speed(f32),                // per second
+    Keyframes(Vec<(f32, f32)>), // (time, value)
+    Trapezoidal { distance: f32, max_speed: f32, acceleration: f32 },
+    SCurve { distance: f32, max_speed: f32 },
+}
+#[derive(Debug)]
+pub enum CurveDecl {
+    Arc { start: Vec3, through: Vec3, end: Vec3 },
+    Polyline(Vec<Vec3>),
+    Bezier(Vec<Vec3>),
+    CatmullRom(Vec<Vec3>),
+}
+#[derive(Debug)]
+pub enum CamProfileDecl {
+    Lift { base_radius: f32, lift: Vec<(f32, f32)> }, // (angle, lift)
+    Polar { base_radius: f32, harmonics: Vec<(f32, f32)> },
+    Spline { points: Vec<(f32, f32)> },
+}
 impl ConstraintDecl {
     pub fn constraint_type(&self) -> &str {
         match self {
             ConstraintDecl::Distance { .. } => "Distance",
+            ConstraintDecl::DistanceRange { .. } => "DistanceRange",
             ConstraintDecl::Fixed { .. } => "Fixed",
             ConstraintDecl::Plane { .. } => "Plane",
             ConstraintDecl::PrismaticVector { .. } => "PrismaticVector",
             ConstraintDecl::PrismaticLink { .. } => "PrismaticLink",
             ConstraintDecl::FixedAngle { .. } => "FixedAngle",
             ConstraintDecl::Revolute { .. } => "Revolute",
+            ConstraintDecl::Drive { .. } => "Drive",
+            ConstraintDecl::Actuator { .. } => "Actuator",
+            ConstraintDecl::Gear { .. } => "Gear",
+            ConstraintDecl::Cam { .. } => "Cam",
+            ConstraintDecl::RackPinion { .. } => "RackPinion",
+            ConstraintDecl::Screw { .. } => "Screw",
+            ConstraintDecl::Belt { .. } => "Belt",
+            ConstraintDecl::OnCurve { .. } => "OnCurve",
+            ConstraintDecl::Slot { .. } => "Slot",
+            ConstraintDecl::Soft { .. } => "Soft",
         }
     }
 }
\ No newline at end of file
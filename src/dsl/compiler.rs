@@ -21,15 +21,23 @@ impl DslCompiler {
             ));
             
             let joint_id = sim.joints.insert(Joint {
+                name: joint_decl.name.clone(),
                 position,
-                joint_type: JointType::Revolute, // Default, could be specified in DSL
+                joint_type: joint_decl.joint_type.as_ref().map(Self::joint_type).unwrap_or_default(),
                 connected_links: Vec::new(),
+                velocity: Vec3::ZERO,
+                inv_mass: 1.0,
             });
             
             // Store in BOTH HashMaps
             joint_name_to_id.insert(joint_decl.name.clone(), joint_id);
         }
         
+        // Masses before anything that grounds a joint
+        for mass_decl in &program.masses {
+            apply_mass(&mut sim, &joint_name_to_id, &mass_decl.joints, mass_decl.mass)?;
+        }
+
         // Second pass: Create links and update joint connections
         let mut link_name_to_id = HashMap::new();
         for link_decl in &program.links {
@@ -41,6 +49,7 @@ impl DslCompiler {
             let link_id = sim.links.insert(Link {
                 joints: vec![*joint_a_id, *joint_b_id],
                 rigid: true,
+                collision: None,
             });
             
             // Update joint connections
@@ -50,33 +59,67 @@ impl DslCompiler {
             link_name_to_id.insert(link_decl.name.clone(), link_id);
 
         }
+
+        // Bodies are links over more joints that also keep their shape
+        for body_decl in &program.bodies {
+            let joint_ids = body_decl.joints.iter()
+                .map(|name| joint_name_to_id.get(name).copied().ok_or_else(|| format!("Joint '{}' not found", name)))
+                .collect::<Result<Vec<JointId>, String>>()?;
+
+            let link_id = sim.links.insert(Link {
+                joints: joint_ids.clone(),
+                rigid: true,
+                collision: None,
+            });
+            for joint_id in &joint_ids {
+                sim.joints.get_mut(*joint_id).unwrap().connected_links.push(link_id);
+            }
+            link_name_to_id.insert(body_decl.name.clone(), link_id);
+
+            apply_body(&mut sim, &joint_name_to_id, &body_decl.joints, body_decl.shape_matching)?;
+        }
         
+        // Collision volumes, one contact constraint keeps all of them apart
+        for capsule in &program.capsules {
+            let link_id = link_name_to_id.get(&capsule.link)
+                .ok_or_else(|| format!("Link '{}' not found", capsule.link))?;
+            if capsule.radius < 0.0 {
+                return Err(format!("Capsule on '{}' has a negative radius", capsule.link));
+            }
+            sim.links[*link_id].collision = Some(Capsule { radius: capsule.radius, layer: capsule.layer });
+        }
+        if !program.capsules.is_empty() {
+            sim.constraints.push(Box::new(ContactConstraint::default()));
+        }
+
+        // Joint types need the links at each joint
+        for joint_decl in &program.joints {
+            apply_joint_type(&mut sim, joint_name_to_id[&joint_decl.name])?;
+        }
+
         // Third pass: Create explicit constraints type shittt
         for constraint_decl in &program.constraints {
-            match constraint_decl {
-                ConstraintDecl::Distance { a, b, value } => {
-                    apply_distance(&mut sim, &joint_name_to_id, a, b, *value)?;
-                }
-                ConstraintDecl::Fixed { joints } => {
-                    apply_fixed(&mut sim, &joint_name_to_id, joints)?;
-                }
-                ConstraintDecl::Plane { joints, normal, point } => {
-                    apply_plane(&mut sim, &joint_name_to_id, joints, *normal, *point)?;
+            Self::compile_constraint(&mut sim, &joint_name_to_id, &link_name_to_id, constraint_decl)?;
+        }
+
+        for force_decl in &program.forces {
+            match force_decl {
+                ForceDecl::Spring { a, b, rest_length, stiffness, damping } => {
+                    apply_spring(&mut sim, &joint_name_to_id, (a, b), *rest_length, (*stiffness, *damping))?;
                 }
-                ConstraintDecl::PrismaticVector { joints, axis, origin } => {
-                    apply_prismatic_vector(&mut sim, &joint_name_to_id, joints, *axis, *origin)?;
+                ForceDecl::TorsionSpring { joint_a, pivot, joint_b, rest_angle, stiffness, damping, axis } => {
+                    apply_torsion_spring(&mut sim, &joint_name_to_id, (joint_a, pivot, joint_b), *rest_angle, (*stiffness, *damping), *axis)?;
                 }
-                ConstraintDecl::PrismaticLink { joints, link, origin } => {
-                    apply_prismatic_link(&mut sim, &joint_name_to_id, &link_name_to_id, joints, link, *origin)?;
+                ForceDecl::Gravity { acceleration } => {
+                    sim.forces.push(Box::new(Gravity { acceleration: *acceleration }));
                 }
-                ConstraintDecl::FixedAngle { joint_a, pivot, joint_c, angle } => {
-                    apply_fixed_angle(&mut sim, &joint_name_to_id, joint_a, pivot, joint_c, *angle)?;
+                ForceDecl::Load { joint, force } => {
+                    apply_load(&mut sim, &joint_name_to_id, joint, *force)?;
                 }
-                ConstraintDecl::Revolute { joint_a, joint_b, axis, min_angle, max_angle } => {
-                    apply_revolute(&mut sim, &joint_name_to_id, joint_a, joint_b, *axis, *min_angle, *max_angle);
+                ForceDecl::Torque { pivot, arm, torque, axis } => {
+                    apply_torque(&mut sim, &joint_name_to_id, (pivot, arm), *torque, *axis)?;
                 }
-                
-        }
+            }
         }
         
         println!("DSL Compilation complete:");
@@ -86,4 +129,138 @@ impl DslCompiler {
         
         Ok(sim)
     }
-}
\ No newline at end of file
+
+    fn compile_constraint(
+        sim: &mut Simulation,
+        joint_name_to_id: &HashMap<String, JointId>,
+        link_name_to_id: &HashMap<String, LinkId>,
+        constraint_decl: &ConstraintDecl,
+    ) -> Result<(), String> {
+        match constraint_decl {
+            ConstraintDecl::Distance { a, b, value } => {
+                apply_distance(sim, joint_name_to_id, a, b, *value)?;
+            }
+            ConstraintDecl::DistanceRange { a, b, min, max } => {
+                apply_distance_range(sim, joint_name_to_id, a, b, (*min, *max))?;
+            }
+            ConstraintDecl::Fixed { joints } => {
+                apply_fixed(sim, joint_name_to_id, joints)?;
+            }
+            ConstraintDecl::Plane { joints, normal, point } => {
+                apply_plane(sim, joint_name_to_id, joints, *normal, *point)?;
+            }
+            ConstraintDecl::PrismaticVector { joints, axis, origin, limits } => {
+                apply_prismatic_vector(sim, joint_name_to_id, joints, *axis, *origin, *limits)?;
+            }
+            ConstraintDecl::PrismaticLink { joints, link, origin } => {
+                apply_prismatic_link(sim, joint_name_to_id, link_name_to_id, joints, link, *origin)?;
+            }
+            ConstraintDecl::FixedAngle { joint_a, pivot, joint_c, angle, keyframes, axis } => {
+                let drive = keyframes.as_ref().map(|points| MotionProfile::Keyframes { points: points.clone() });
+                apply_fixed_angle(sim, joint_name_to_id, (joint_a, pivot, joint_c), *angle, drive, *axis)?;
+            }
+            ConstraintDecl::Revolute { joint_a, joint_b, axis, min_angle, max_angle, link } => {
+                apply_revolute(
+                    sim,
+                    joint_name_to_id,
+                    link_name_to_id,
+                    (joint_a, joint_b),
+                    *axis,
+                    (*min_angle, *max_angle),
+                    link.as_deref(),
+                )?;
+            }
+            ConstraintDecl::Drive { pivot, moving, profile, axis } => {
+                apply_drive(sim, joint_name_to_id, pivot, moving, *axis, Self::motion_profile(profile))?;
+            }
+            ConstraintDecl::Actuator { joint, axis, profile } => {
+                apply_actuator(sim, joint_name_to_id, joint, *axis, Self::motion_profile(profile))?;
+            }
+            ConstraintDecl::Gear { pivot_a, arm_a, pivot_b, arm_b, ratio, axis } => {
+                apply_gear(sim, joint_name_to_id, (pivot_a, arm_a), (pivot_b, arm_b), *ratio, *axis)?;
+            }
+            ConstraintDecl::Cam { pivot, arm, follower, profile, roller_radius, axis } => {
+                apply_cam(sim, joint_name_to_id, (pivot, arm), follower, Self::cam_profile(profile), *roller_radius, *axis)?;
+            }
+            ConstraintDecl::RackPinion { pivot, arm, rack, pitch_radius, direction, axis } => {
+                apply_rack_pinion(sim, joint_name_to_id, (pivot, arm), rack, *pitch_radius, *direction, *axis)?;
+            }
+            ConstraintDecl::Screw { pivot, arm, nut, lead, axis } => {
+                apply_screw(sim, joint_name_to_id, (pivot, arm), nut, *lead, *axis)?;
+            }
+            ConstraintDecl::Belt { pulleys, crossed, axis } => {
+                apply_belt(sim, joint_name_to_id, pulleys, *crossed, *axis)?;
+            }
+            ConstraintDecl::OnCurve { joint, curve, link, axis } => {
+                let curve = match curve {
+                    CurveDecl::Arc { start, through, end } => PathCurve::arc_through(*start, *through, *end)
+                        .ok_or_else(|| format!("Arc points for '{}' are collinear", joint))?,
+                    CurveDecl::Polyline(points) => PathCurve::Polyline { points: points.clone() },
+                    CurveDecl::Bezier(points) => PathCurve::Bezier { points: points.clone() },
+                    CurveDecl::CatmullRom(points) => PathCurve::CatmullRom { points: points.clone() },
+                };
+                apply_point_on_curve(sim, joint_name_to_id, link_name_to_id, joint, curve, link.as_deref(), *axis)?;
+            }
+            ConstraintDecl::Slot { pin, link, points, axis } => {
+                apply_slot(sim, joint_name_to_id, link_name_to_id, pin, link, points, *axis)?;
+            }
+            ConstraintDecl::Soft { constraint, compliance } => {
+                // everything the wrapped declaration pushes gets the compliance
+                let first = sim.constraints.len();
+                Self::compile_constraint(sim, joint_name_to_id, link_name_to_id, constraint)?;
+                for constraint in &mut sim.constraints[first..] {
+                    constraint.xpbd_mut().compliance = *compliance;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn joint_type(joint_type: &JointTypeDecl) -> JointType {
+        match joint_type {
+            JointTypeDecl::Fixed => JointType::Fixed,
+            JointTypeDecl::Revolute { axis, limits } => JointType::Revolute { axis: *axis, limits: *limits },
+            JointTypeDecl::Slider { axis, limits } => JointType::Slider { axis: *axis, limits: *limits },
+            JointTypeDecl::Spherical { cone } => JointType::Spherical { cone: *cone },
+            JointTypeDecl::Universal { axis_a, axis_b, limit } => JointType::Universal {
+                axis_a: *axis_a,
+                axis_b: *axis_b,
+                limit: *limit,
+            },
+            JointTypeDecl::Cylindrical { axis, limits } => JointType::Cylindrical { axis: *axis, limits: *limits },
+            JointTypeDecl::Planar { normal } => JointType::Planar { normal: *normal },
+        }
+    }
+
+    fn motion_profile(profile: &ProfileDecl) -> MotionProfile {
+        match profile {
+            ProfileDecl::Speed(speed) => MotionProfile::ConstantSpeed { speed: *speed },
+            ProfileDecl::Keyframes(points) => MotionProfile::Keyframes { points: points.clone() },
+            ProfileDecl::Trapezoidal { distance, max_speed, acceleration } => MotionProfile::Trapezoidal {
+                distance: *distance,
+                max_speed: *max_speed,
+                acceleration: *acceleration,
+            },
+            ProfileDecl::SCurve { distance, max_speed } => MotionProfile::SCurve {
+                distance: *distance,
+                max_speed: *max_speed,
+            },
+        }
+    }
+
+    fn cam_profile(profile: &CamProfileDecl) -> CamProfile {
+        match profile {
+            CamProfileDecl::Lift { base_radius, lift } => CamProfile::RadialLift {
+                base_radius: *base_radius,
+                lift: lift.clone(),
+            },
+            CamProfileDecl::Polar { base_radius, harmonics } => CamProfile::Polar {
+                base_radius: *base_radius,
+                harmonics: harmonics.clone(),
+            },
+            CamProfileDecl::Spline { points } => CamProfile::Spline {
+                points: points.iter().map(|(x, y)| glam::Vec2::new(*x, *y)).collect(),
+            },
+        }
+    }
+}
@@ -23,6 +23,10 @@ pub fn parse_program(mut pairs: Pairs<Rule>) -> Result<Program, Box<dyn std::err
     
     let mut joints = Vec::new();
     let mut links = Vec::new();
+    let mut bodies = Vec::new();
+    let mut capsules = Vec::new();
+    let mut masses = Vec::new();
+    let mut forces = Vec::new();
     let mut constraints = Vec::new();
     for statement in inner {
         // Get the inner Pair (joint_decl, link_decl, or constraint_decl)
@@ -31,8 +35,8 @@ pub fn parse_program(mut pairs: Pairs<Rule>) -> Result<Program, Box<dyn std::err
             Rule::joint_decl => {
                 let joint = parse_joint_decl(inner_pair)?;
                 println!(
-                    "Parsed joint: name={}, position={:?}",
-                    joint.name, joint.position
+                    "Parsed joint: name={}, position={:?}, type={:?}",
+                    joint.name, joint.position, joint.joint_type
                 );
                 joints.push(joint);
             }
@@ -44,6 +48,22 @@ pub fn parse_program(mut pairs: Pairs<Rule>) -> Result<Program, Box<dyn std::err
                 );
                 links.push(link);
             }
+            Rule::body_decl => {
+                let body = parse_body_decl(inner_pair)?;
+                bodies.push(body);
+            }
+            Rule::capsule_decl => {
+                let capsule = parse_capsule_decl(inner_pair)?;
+                capsules.push(capsule);
+            }
+            Rule::mass_decl => {
+                let mass = parse_mass_decl(inner_pair)?;
+                masses.push(mass);
+            }
+            Rule::force_decl => {
+                let force = parse_force_decl(inner_pair)?;
+                forces.push(force);
+            }
             Rule::constraint_decl => {
                 let constraint = parse_constraint_decl(inner_pair)?;
                 println!(
@@ -71,6 +91,10 @@ pub fn parse_program(mut pairs: Pairs<Rule>) -> Result<Program, Box<dyn std::err
         sim_name,
         joints,
         links,
+        bodies,
+        capsules,
+        masses,
+        forces,
         constraints,
     })
 }
@@ -78,13 +102,57 @@ fn parse_joint_decl(pair: Pair<Rule>) -> Result<JointDecl, Box<dyn std::error::E
     let mut inner = pair.into_inner();
     let name = inner.next().unwrap().as_str().to_string();
     
-    let x: f32 = inner.next().unwrap().as_str().parse()?;
-    let y: f32 = inner.next().unwrap().as_str().parse()?;
-    let z: f32 = inner.next().map(|p| p.as_str().parse()).transpose()?.unwrap_or(0.0);
+    let mut position = [0.0f32; 3];
+    let mut joint_type = None;
+    for (i, param) in inner.enumerate() {
+        match param.as_rule() {
+            Rule::joint_type => joint_type = Some(parse_joint_type(param)?),
+            _ => position[i] = param.as_str().parse()?,
+        }
+    }
     
     Ok(JointDecl {
         name,
-        position: [x, y, z],
+        position,
+        joint_type,
+    })
+}
+
+fn parse_joint_type(pair: Pair<Rule>) -> Result<JointTypeDecl, Box<dyn std::error::Error>> {
+    let kind = pair.into_inner().next().unwrap();
+    let rule = kind.as_rule();
+    let mut inner = kind.into_inner();
+
+    Ok(match rule {
+        Rule::fixed_joint => JointTypeDecl::Fixed,
+        Rule::revolute_joint => {
+            let axis_param = inner.next();
+            let limits = match (inner.next(), inner.next()) {
+                (Some(min), Some(max)) => Some((parse_angle_value(min)?, parse_angle_value(max)?)),
+                _ => None,
+            };
+            JointTypeDecl::Revolute { axis: axis_param.map(parse_axis_param).transpose()?.unwrap_or(Vec3::Z), limits }
+        }
+        Rule::spherical_joint => JointTypeDecl::Spherical { cone: inner.next().map(parse_angle_value).transpose()? },
+        Rule::universal_joint => {
+            let axis_a = parse_axis_param(inner.next().unwrap())?;
+            let axis_b = parse_axis_param(inner.next().unwrap())?;
+            JointTypeDecl::Universal { axis_a, axis_b, limit: inner.next().map(parse_angle_value).transpose()? }
+        }
+        Rule::planar_joint => JointTypeDecl::Planar { normal: parse_axis_param(inner.next().unwrap())? },
+        _ => {
+            // slider and cylindrical, an axis and the travel limits
+            let axis = parse_axis_param(inner.next().unwrap())?;
+            let limits = match (inner.next(), inner.next()) {
+                (Some(min), Some(max)) => Some((parse_distance(min)?, parse_distance(max)?)),
+                _ => None,
+            };
+            if rule == Rule::slider_joint {
+                JointTypeDecl::Slider { axis, limits }
+            } else {
+                JointTypeDecl::Cylindrical { axis, limits }
+            }
+        }
     })
 }
 
@@ -101,18 +169,152 @@ fn parse_link_decl(pair: Pair<Rule>) -> Result<LinkDecl, Box<dyn std::error::Err
     })
 }
 
+fn parse_capsule_decl(pair: Pair<Rule>) -> Result<CapsuleDecl, Box<dyn std::error::Error>> {
+    let mut inner = pair.into_inner();
+    let link = inner.next().unwrap().as_str().to_string();
+    let radius = parse_distance(inner.next().unwrap())?;
+    let layer = inner.next().map(|p| p.as_str().parse()).transpose()?.unwrap_or(0);
+
+    Ok(CapsuleDecl { link, radius, layer })
+}
+
+fn parse_mass_decl(pair: Pair<Rule>) -> Result<MassDecl, Box<dyn std::error::Error>> {
+    let mut inner = pair.into_inner();
+    let joints = parse_identifier_list(inner.next().unwrap());
+    let mass: f32 = inner.next().unwrap().as_str().parse()?;
+
+    Ok(MassDecl { joints, mass })
+}
+
+fn parse_force_decl(pair: Pair<Rule>) -> Result<ForceDecl, Box<dyn std::error::Error>> {
+    let force = pair.into_inner().next().unwrap();
+    match force.as_rule() {
+        Rule::spring_decl => {
+            let mut inner = force.into_inner();
+            let a = inner.next().unwrap().as_str().to_string();
+            let b = inner.next().unwrap().as_str().to_string();
+            let rest_length = parse_distance(inner.next().unwrap())?;
+            let stiffness: f32 = inner.next().unwrap().as_str().parse()?;
+            let damping: f32 = inner.next().map(|p| p.as_str().parse()).transpose()?.unwrap_or(0.0);
+
+            Ok(ForceDecl::Spring { a, b, rest_length, stiffness, damping })
+        }
+        Rule::damper_decl => {
+            let mut inner = force.into_inner();
+            let a = inner.next().unwrap().as_str().to_string();
+            let b = inner.next().unwrap().as_str().to_string();
+            let damping: f32 = inner.next().unwrap().as_str().parse()?;
+
+            // a damper is a spring with no stiffness, its rest length never matters
+            Ok(ForceDecl::Spring { a, b, rest_length: 0.0, stiffness: 0.0, damping })
+        }
+        Rule::torsion_spring_decl => {
+            let mut inner = force.into_inner();
+            let joint_a = inner.next().unwrap().as_str().to_string();
+            let pivot = inner.next().unwrap().as_str().to_string();
+            let joint_b = inner.next().unwrap().as_str().to_string();
+            let rest_angle = parse_angle_value(inner.next().unwrap())?;
+            let stiffness: f32 = inner.next().unwrap().as_str().parse()?;
+
+            let (mut damping, mut axis) = (0.0, Vec3::Z);
+            for param in inner {
+                match param.as_rule() {
+                    Rule::number => damping = param.as_str().parse()?,
+                    _ => axis = parse_axis_param(param)?,
+                }
+            }
+
+            Ok(ForceDecl::TorsionSpring { joint_a, pivot, joint_b, rest_angle, stiffness, damping, axis })
+        }
+        Rule::gravity_decl => {
+            let param = force.into_inner().next().unwrap();
+            let acceleration = match param.as_rule() {
+                Rule::number => Vec3::new(0.0, -param.as_str().parse::<f32>()?, 0.0),
+                _ => parse_vec3(param)?,
+            };
+
+            Ok(ForceDecl::Gravity { acceleration })
+        }
+        Rule::load_decl => {
+            let mut inner = force.into_inner();
+            let joint = inner.next().unwrap().as_str().to_string();
+            let force = parse_vec3(inner.next().unwrap())?;
+
+            Ok(ForceDecl::Load { joint, force })
+        }
+        Rule::torque_decl => {
+            let mut inner = force.into_inner();
+            let pivot = inner.next().unwrap().as_str().to_string();
+            let arm = inner.next().unwrap().as_str().to_string();
+            let torque: f32 = inner.next().unwrap().as_str().parse()?;
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
+            };
+
+            Ok(ForceDecl::Torque { pivot, arm, torque, axis })
+        }
+        _ => Err(format!("Unknown force element: {:?}", force.as_rule()).into()),
+    }
+}
+
+fn parse_body_decl(pair: Pair<Rule>) -> Result<BodyDecl, Box<dyn std::error::Error>> {
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str().to_string();
+
+    let mut joints = Vec::new();
+    let mut shape_matching = false;
+    for param in inner {
+        match param.as_rule() {
+            Rule::body_mode => shape_matching = param.as_str() == "shape_match",
+            _ => joints.push(param.as_str().to_string()),
+        }
+    }
+
+    Ok(BodyDecl {
+        name,
+        joints,
+        shape_matching,
+    })
+}
+
 fn parse_constraint_decl(pair: Pair<Rule>) -> Result<ConstraintDecl, Box<dyn std::error::Error>> {
-    let constraint = pair.into_inner().next().unwrap();
-    
+    let mut inner = pair.into_inner();
+    let constraint = parse_constraint(inner.next().unwrap())?;
+
+    // Optional compliance suffix wraps the constraint
+    match inner.next() {
+        Some(compliance_pair) => {
+            let compliance: f32 = compliance_pair.into_inner().next().unwrap().as_str().parse()?;
+            Ok(ConstraintDecl::Soft { constraint: Box::new(constraint), compliance })
+        }
+        None => Ok(constraint),
+    }
+}
+
+fn parse_constraint(constraint: Pair<Rule>) -> Result<ConstraintDecl, Box<dyn std::error::Error>> {
     match constraint.as_rule() {
         Rule::distance_constraint => {
             let mut inner = constraint.into_inner();
             let a = inner.next().unwrap().as_str    ().to_string();
             let b = inner.next().unwrap().as_str().to_string();
-            let value: f32 = inner.next().unwrap().as_str().parse()?;
+            let value_param = inner.next().unwrap();
+            if value_param.as_rule() == Rule::range {
+                let (min, max) = parse_range(value_param)?;
+                return Ok(ConstraintDecl::DistanceRange { a, b, min: min.max(0.0), max });
+            }
+            let value: f32 = value_param.as_str().parse()?;
             
             Ok(ConstraintDecl::Distance { a, b, value })
         }
+        Rule::rope_constraint => {
+            let mut inner = constraint.into_inner();
+            let a = inner.next().unwrap().as_str().to_string();
+            let b = inner.next().unwrap().as_str().to_string();
+            let length: f32 = inner.next().unwrap().as_str().parse()?;
+
+            Ok(ConstraintDecl::DistanceRange { a, b, min: 0.0, max: length })
+        }
         Rule::fixed_constraint => {
             let mut inner = constraint.into_inner();
             let identifier_list = inner.next().unwrap();
@@ -182,8 +384,10 @@ fn parse_constraint_decl(pair: Pair<Rule>) -> Result<ConstraintDecl, Box<dyn std
             let y: f32 = vec_inner.next().unwrap().as_str().parse()?;
             let z: f32 = vec_inner.next().unwrap().as_str().parse()?;
             let origin = Vec3::new(x, y, z);
+
+            let limits = inner.next().map(parse_range).transpose()?;
             
-            Ok(ConstraintDecl::PrismaticVector { joints, axis, origin })
+            Ok(ConstraintDecl::PrismaticVector { joints, axis, origin, limits })
         }
         Rule::prismatic_constraint_link => {
             let mut inner = constraint.into_inner();
@@ -211,63 +415,182 @@ fn parse_constraint_decl(pair: Pair<Rule>) -> Result<ConstraintDecl, Box<dyn std
             let joint_c = inner.next().unwrap().as_str().to_string();
             
             let angle_pair = inner.next().unwrap();
-            let mut number_value = 0.0f32;
-            let mut is_degrees = false;
-        
-            for inner_pair in angle_pair.into_inner() {
-                match inner_pair.as_rule() {
-                    Rule::number => {
-                        number_value = inner_pair.as_str().parse()?;
-                    }
-                    Rule::angle_unit => {
-                        match inner_pair.as_str() {
-                            "deg" | "degrees" => is_degrees = true,
-                            "rad" | "radians" => is_degrees = false,
-                            _ => {} // fallback is radians
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        
-            let angle = if is_degrees {
-                number_value.to_radians()
-            } else {
-                number_value
+            let (angle, keyframes) = match angle_pair.as_rule() {
+                Rule::keyframes => (0.0, Some(parse_keyframes(angle_pair, parse_angle_value)?)),
+                _ => (parse_angle_value(angle_pair)?, None),
+            };
+
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
             };
-        
-            Ok(ConstraintDecl::FixedAngle { joint_a, pivot, joint_c, angle })
+
+            Ok(ConstraintDecl::FixedAngle { joint_a, pivot, joint_c, angle, keyframes, axis })
         }
         Rule::revolute_constraint => {
             let mut inner = constraint.into_inner();
             let joint_pivot = inner.next().unwrap().as_str().to_string();
             let joint_moving = inner.next().unwrap().as_str().to_string();
             
-            let axis_param = inner.next().unwrap();
-            let axis = match axis_param.as_str() {
-                "X" => Vec3::X,
-                "Y" => Vec3::Y,
-                "Z" => Vec3::Z,
-                _ => {
-                    // Parse as Vec3 tuple
-                    let mut vec_inner = axis_param.into_inner();
-                    let x: f32 = vec_inner.next().unwrap().as_str().parse()?;
-                    let y: f32 = vec_inner.next().unwrap().as_str().parse()?;
-                    let z: f32 = vec_inner.next().unwrap().as_str().parse()?;
-                    Vec3::new(x, y, z)
+            let axis = parse_axis_param(inner.next().unwrap())?;
+
+            // a hinge without limits turns freely
+            let (mut min_angle, mut max_angle) = (-std::f32::consts::PI, std::f32::consts::PI);
+            let mut link = None;
+            while let Some(param) = inner.next() {
+                match param.as_rule() {
+                    Rule::angle_value => {
+                        min_angle = parse_angle_value(param)?;
+                        max_angle = parse_angle_value(inner.next().unwrap())?;
+                    }
+                    _ => link = Some(param.as_str().to_string()),
                 }
-            };
-            let min_angle = inner.next().unwrap().as_str().parse()?;
-            let max_angle = inner.next().unwrap().as_str().parse()?;
-           
+            }
+
             Ok(ConstraintDecl::Revolute {
                 joint_a: joint_pivot,
                 joint_b: joint_moving,
                 axis,
                 min_angle,
                 max_angle,
+                link,
             })
         }
+        Rule::drive_constraint => {
+            let mut inner = constraint.into_inner();
+            let pivot = inner.next().unwrap().as_str().to_string();
+            let moving = inner.next().unwrap().as_str().to_string();
+
+            let profile_pair = inner.next().unwrap();
+            let profile = match profile_pair.as_rule() {
+                Rule::keyframes => ProfileDecl::Keyframes(parse_keyframes(profile_pair, parse_angle_value)?),
+                _ => ProfileDecl::Speed(parse_angle_value(profile_pair)?),
+            };
+
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
+            };
+
+            Ok(ConstraintDecl::Drive { pivot, moving, profile, axis })
+        }
+        Rule::actuator_constraint => {
+            let mut inner = constraint.into_inner();
+            let joint = inner.next().unwrap().as_str().to_string();
+            let axis = parse_axis_param(inner.next().unwrap())?;
+            let profile = parse_linear_profile(inner.next().unwrap())?;
+
+            Ok(ConstraintDecl::Actuator { joint, axis, profile })
+        }
+        Rule::gear_constraint => {
+            let mut inner = constraint.into_inner();
+            let pivot_a = inner.next().unwrap().as_str().to_string();
+            let arm_a = inner.next().unwrap().as_str().to_string();
+            let pivot_b = inner.next().unwrap().as_str().to_string();
+            let arm_b = inner.next().unwrap().as_str().to_string();
+            let ratio: f32 = inner.next().unwrap().as_str().parse()?;
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
+            };
+
+            Ok(ConstraintDecl::Gear { pivot_a, arm_a, pivot_b, arm_b, ratio, axis })
+        }
+        Rule::cam_constraint => {
+            let mut inner = constraint.into_inner();
+            let pivot = inner.next().unwrap().as_str().to_string();
+            let arm = inner.next().unwrap().as_str().to_string();
+            let follower = inner.next().unwrap().as_str().to_string();
+            let profile = parse_cam_profile(inner.next().unwrap())?;
+
+            let mut roller_radius = 0.0;
+            let mut axis = Vec3::Z;
+            for param in inner {
+                match param.as_rule() {
+                    Rule::number => roller_radius = param.as_str().parse()?,
+                    _ => axis = parse_axis_param(param)?,
+                }
+            }
+
+            Ok(ConstraintDecl::Cam { pivot, arm, follower, profile, roller_radius, axis })
+        }
+        Rule::rack_constraint => {
+            let mut inner = constraint.into_inner();
+            let pivot = inner.next().unwrap().as_str().to_string();
+            let arm = inner.next().unwrap().as_str().to_string();
+            let rack = inner.next().unwrap().as_str().to_string();
+            let pitch_radius: f32 = inner.next().unwrap().as_str().parse()?;
+            let direction = parse_axis_param(inner.next().unwrap())?;
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
+            };
+
+            Ok(ConstraintDecl::RackPinion { pivot, arm, rack, pitch_radius, direction, axis })
+        }
+        Rule::screw_constraint => {
+            let mut inner = constraint.into_inner();
+            let pivot = inner.next().unwrap().as_str().to_string();
+            let arm = inner.next().unwrap().as_str().to_string();
+            let nut = inner.next().unwrap().as_str().to_string();
+            let lead: f32 = inner.next().unwrap().as_str().parse()?;
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
+            };
+
+            Ok(ConstraintDecl::Screw { pivot, arm, nut, lead, axis })
+        }
+        Rule::belt_constraint => {
+            let mut pulleys = Vec::new();
+            let mut crossed = false;
+            let mut axis = Vec3::Z;
+            for param in constraint.into_inner() {
+                match param.as_rule() {
+                    Rule::pulley => {
+                        let mut inner = param.into_inner();
+                        let pivot = inner.next().unwrap().as_str().to_string();
+                        let arm = inner.next().unwrap().as_str().to_string();
+                        let radius: f32 = inner.next().unwrap().as_str().parse()?;
+                        pulleys.push((pivot, arm, radius));
+                    }
+                    Rule::belt_kind => crossed = param.as_str() == "crossed",
+                    _ => axis = parse_axis_param(param)?,
+                }
+            }
+
+            Ok(ConstraintDecl::Belt { pulleys, crossed, axis })
+        }
+        Rule::curve_constraint => {
+            let mut inner = constraint.into_inner();
+            let joint = inner.next().unwrap().as_str().to_string();
+            let curve = parse_curve(inner.next().unwrap())?;
+            let link = inner.next().map(|link| link.as_str().to_string());
+            let axis = match inner.next() {
+                Some(axis_param) => parse_axis_param(axis_param)?,
+                None => Vec3::Z,
+            };
+
+            Ok(ConstraintDecl::OnCurve { joint, curve, link, axis })
+        }
+        Rule::slot_constraint => {
+            let mut inner = constraint.into_inner();
+            let pin = inner.next().unwrap().as_str().to_string();
+            let link = inner.next().unwrap().as_str().to_string();
+            let mut points = Vec::new();
+            let mut axis = Vec3::Z;
+            for param in inner {
+                match param.as_rule() {
+                    Rule::point2 => {
+                        let mut point = param.into_inner();
+                        points.push((parse_distance(point.next().unwrap())?, parse_distance(point.next().unwrap())?));
+                    }
+                    _ => axis = parse_axis_param(param)?,
+                }
+            }
+
+            Ok(ConstraintDecl::Slot { pin, link, points, axis })
+        }
         _ => Err("Unknown constraint type".into())
 
     }
@@ -277,4 +600,158 @@ fn parse_identifier_list(pair: Pair<Rule>) -> Vec<String> {
     pair.into_inner()
         .map(|p| p.as_str().to_string())
         .collect()
-}
\ No newline at end of file
+}
+
+// angle_value, radians unless a deg/degrees unit is given
+fn parse_angle_value(pair: Pair<Rule>) -> Result<f32, Box<dyn std::error::Error>> {
+    let mut value = 0.0f32;
+    let mut is_degrees = false;
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::number => value = inner_pair.as_str().parse()?,
+            Rule::angle_unit => is_degrees = matches!(inner_pair.as_str(), "deg" | "degrees"),
+            _ => {}
+        }
+    }
+    Ok(if is_degrees { value.to_radians() } else { value })
+}
+
+// Either an axis letter or a vec3 tuple
+fn parse_axis_param(pair: Pair<Rule>) -> Result<Vec3, Box<dyn std::error::Error>> {
+    match pair.as_str() {
+        "X" => Ok(Vec3::X),
+        "Y" => Ok(Vec3::Y),
+        "Z" => Ok(Vec3::Z),
+        _ => parse_vec3(pair),
+    }
+}
+
+// min..max with either end left open
+fn parse_range(pair: Pair<Rule>) -> Result<(f32, f32), Box<dyn std::error::Error>> {
+    let (mut min, mut max) = (f32::NEG_INFINITY, f32::INFINITY);
+    for bound in pair.into_inner() {
+        let value: f32 = bound.clone().into_inner().next().unwrap().as_str().parse()?;
+        match bound.as_rule() {
+            Rule::range_min => min = value,
+            Rule::range_max => max = value,
+            _ => {}
+        }
+    }
+    if min > max {
+        return Err(format!("Range {}..{} is empty", min, max).into());
+    }
+    if min.is_infinite() && max.is_infinite() {
+        return Err("Range .. needs at least one end".into());
+    }
+    Ok((min, max))
+}
+
+fn parse_vec3(pair: Pair<Rule>) -> Result<Vec3, Box<dyn std::error::Error>> {
+    let mut vec_inner = pair.into_inner();
+    let x: f32 = vec_inner.next().unwrap().as_str().parse()?;
+    let y: f32 = vec_inner.next().unwrap().as_str().parse()?;
+    let z: f32 = vec_inner.next().unwrap().as_str().parse()?;
+    Ok(Vec3::new(x, y, z))
+}
+
+type ValueParser = fn(Pair<Rule>) -> Result<f32, Box<dyn std::error::Error>>;
+
+// [(time, value), ...], value parsed by `parse_value`
+fn parse_keyframes(
+    pair: Pair<Rule>,
+    parse_value: ValueParser,
+) -> Result<Vec<(f32, f32)>, Box<dyn std::error::Error>> {
+    let mut points = Vec::new();
+    for keyframe in pair.into_inner() {
+        let mut inner = keyframe.into_inner();
+        let time: f32 = inner.next().unwrap().as_str().parse()?;
+        let value = parse_value(inner.next().unwrap())?;
+        points.push((time, value));
+    }
+    Ok(points)
+}
+
+fn parse_distance(pair: Pair<Rule>) -> Result<f32, Box<dyn std::error::Error>> {
+    Ok(pair.as_str().parse()?)
+}
+
+fn parse_linear_profile(pair: Pair<Rule>) -> Result<ProfileDecl, Box<dyn std::error::Error>> {
+    let profile = pair.into_inner().next().unwrap();
+    match profile.as_rule() {
+        Rule::number => Ok(ProfileDecl::Speed(parse_distance(profile)?)),
+        Rule::distance_keyframes => Ok(ProfileDecl::Keyframes(parse_keyframes(profile, parse_distance)?)),
+        Rule::trapezoid_profile => {
+            let mut inner = profile.into_inner();
+            let distance = parse_distance(inner.next().unwrap())?;
+            let max_speed = parse_distance(inner.next().unwrap())?;
+            let acceleration = parse_distance(inner.next().unwrap())?;
+            Ok(ProfileDecl::Trapezoidal { distance, max_speed, acceleration })
+        }
+        Rule::scurve_profile => {
+            let mut inner = profile.into_inner();
+            let distance = parse_distance(inner.next().unwrap())?;
+            let max_speed = parse_distance(inner.next().unwrap())?;
+            Ok(ProfileDecl::SCurve { distance, max_speed })
+        }
+        _ => Err("Unknown motion profile".into()),
+    }
+}
+
+fn parse_cam_profile(pair: Pair<Rule>) -> Result<CamProfileDecl, Box<dyn std::error::Error>> {
+    let profile = pair.into_inner().next().unwrap();
+    let rule = profile.as_rule();
+    let mut inner = profile.into_inner();
+    match rule {
+        Rule::lift_profile => {
+            let base_radius = parse_distance(inner.next().unwrap())?;
+            let mut lift = Vec::new();
+            for point in inner {
+                let mut point = point.into_inner();
+                let angle = parse_angle_value(point.next().unwrap())?;
+                lift.push((angle, parse_distance(point.next().unwrap())?));
+            }
+            Ok(CamProfileDecl::Lift { base_radius, lift })
+        }
+        Rule::polar_profile => {
+            let base_radius = parse_distance(inner.next().unwrap())?;
+            let harmonics = match inner.next() {
+                Some(list) => parse_point_list(list)?,
+                None => Vec::new(),
+            };
+            Ok(CamProfileDecl::Polar { base_radius, harmonics })
+        }
+        Rule::spline_profile => Ok(CamProfileDecl::Spline { points: parse_point_list(inner.next().unwrap())? }),
+        _ => Err("Unknown cam profile".into()),
+    }
+}
+
+fn parse_point_list(pair: Pair<Rule>) -> Result<Vec<(f32, f32)>, Box<dyn std::error::Error>> {
+    pair.into_inner()
+        .map(|point| {
+            let mut inner = point.into_inner();
+            Ok((parse_distance(inner.next().unwrap())?, parse_distance(inner.next().unwrap())?))
+        })
+        .collect()
+}
+
+fn parse_curve(pair: Pair<Rule>) -> Result<CurveDecl, Box<dyn std::error::Error>> {
+    let curve = pair.into_inner().next().unwrap();
+    let rule = curve.as_rule();
+    let mut inner = curve.into_inner();
+    if rule == Rule::arc_curve {
+        let start = parse_vec3(inner.next().unwrap())?;
+        let through = parse_vec3(inner.next().unwrap())?;
+        let end = parse_vec3(inner.next().unwrap())?;
+        return Ok(CurveDecl::Arc { start, through, end });
+    }
+
+    let points = inner.next().unwrap().into_inner()
+        .map(parse_vec3)
+        .collect::<Result<Vec<Vec3>, _>>()?;
+    match rule {
+        Rule::polyline_curve => Ok(CurveDecl::Polyline(points)),
+        Rule::bezier_curve => Ok(CurveDecl::Bezier(points)),
+        Rule::catmull_rom_curve => Ok(CurveDecl::CatmullRom(points)),
+        _ => Err("Unknown curve".into()),
+    }
+}
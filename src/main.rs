@@ -12,8 +12,14 @@ use crate::util::world::*;
 use crate::util::interact::*;
 use crate::util::simulation::*;
 use crate::simcore::types::*;
+use crate::simcore::solvers::{solver_backends, Solver};
 use crate::dsl::*;
 use crate::util::keybindings::*;
+use crate::util::singularity::*;
+use crate::util::interference::*;
+use crate::util::mobility::*;
+use crate::util::statics::*;
+use crate::util::kinematics::*;
 
 
 #[derive(Resource, Default)]
@@ -31,6 +37,7 @@ pub struct FilePath {
 pub struct TextState {
     pub content: String,
     pub other_speed_string: String,
+    pub tolerance_string: String,
 }
 
 
@@ -43,6 +50,8 @@ fn main() {
         
         .add_event::<PickedJoint>()
         .add_event::<MoveJoint>()        
+        .add_event::<SingularityEntered>()
+        .add_event::<SingularityLeft>()
         .add_plugins(DefaultPlugins)
         .add_plugins(EguiPlugin {
             enable_multipass_for_primary_context: true,
@@ -55,8 +64,15 @@ fn main() {
         .insert_resource(ListeningState::default())
         .insert_resource(FilePath::default())
         .insert_resource(KeyBindings::default())
+        .insert_resource(SingularityState::default())
+        .insert_resource(InterferenceState::default())
+        .insert_resource(MobilityState::default())
+        .insert_resource(StaticsState::default())
+        .insert_resource(KinematicsState::default())
         .insert_resource(SimWrapper {
             sim: Simulation::default(),
+            running: false,
+            last_report: SolveReport::default(),
         })
         .add_systems(
 
@@ -77,9 +93,24 @@ fn main() {
             highlight_system,  
             reset_on_release_system,
             joint_drag_system,
+            play_pause_system.before(sim_step_system),
             sim_step_system,
             update_joint_visuals.after(sim_step_system),
             update_link_visuals.after(sim_step_system),
+            update_plate_visuals.after(sim_step_system),
+            constraint_gizmo_system.after(sim_step_system),
+        ))
+        .add_systems(Update, (
+            singularity_detection_system.after(sim_step_system),
+            singularity_log_system.after(singularity_detection_system),
+            singularity_gizmo_system.after(singularity_detection_system),
+            interference_record_system.after(sim_step_system),
+            interference_gizmo_system.after(interference_record_system),
+            mobility_invalidate_system.after(sim_step_system),
+            statics_system.after(sim_step_system),
+            statics_gizmo_system.after(statics_system),
+            kinematics_system.after(sim_step_system),
+            kinematics_gizmo_system.after(kinematics_system),
         ))
         .add_systems(EguiContextPass, ui_example_system)
         .add_systems(EguiContextPass, keybindings_ui)
@@ -141,6 +172,11 @@ fn ui_example_system(
     mut text_state: ResMut<TextState>,
     mut file_path: ResMut<FilePath>,
     mut input_focus: ResMut<InputFocus>,
+    mut singularity_state: ResMut<SingularityState>,
+    mut interference: ResMut<InterferenceState>,
+    mut mobility_state: ResMut<MobilityState>,
+    mut statics: ResMut<StaticsState>,
+    mut kinematics: ResMut<KinematicsState>,
 
 ) { 
     let ctx = contexts.ctx_mut();
@@ -171,6 +207,203 @@ fn ui_example_system(
             );
 
        
+            ui.horizontal(|ui| {
+                let play_label = if sim_wrapper.running { "Pause" } else { "Play" };
+                if ui.button(play_label).clicked() {
+                    sim_wrapper.running = !sim_wrapper.running;
+                }
+                if ui.button("Rewind").clicked() {
+                    // drivers jump back to their start, don't let that turn into velocity
+                    sim_wrapper.sim.time = 0.0;
+                    sim_wrapper.sim.reset_velocities();
+                }
+                if ui.button("Stop motion").clicked() {
+                    sim_wrapper.sim.reset_velocities();
+                }
+                ui.label(format!("t = {:.2}s", sim_wrapper.sim.time));
+            });
+            egui::ComboBox::from_label("solver")
+                .selected_text(sim_wrapper.sim.solver.name())
+                .show_ui(ui, |ui| {
+                    for backend in solver_backends() {
+                        let selected = backend.name() == sim_wrapper.sim.solver.name();
+                        if ui.selectable_label(selected, backend.name()).clicked() {
+                            sim_wrapper.sim.solver = backend;
+                        }
+                    }
+                });
+
+            let report = &sim_wrapper.last_report;
+            ui.label(format!(
+                "{} after {}/{} iterations, max residual {:.2e}",
+                if report.converged { "converged" } else { "NOT converged" },
+                report.iterations,
+                report.max_iterations,
+                report.max_residual,
+            ));
+            for (index, residual) in report.violated.iter().take(8) {
+                if let Some(constraint) = sim_wrapper.sim.constraints.get(*index) {
+                    ui.colored_label(
+                        egui::Color32::LIGHT_RED,
+                        format!("  #{} {} off by {:.2e}", index, constraint.type_name(), residual),
+                    );
+                }
+            }
+
+            if let Some(singularity) = &singularity_state.active {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 0, 255),
+                    format!("{:?} (measure {:.4})", singularity.kind, singularity.measure),
+                );
+            }
+
+            ui.collapsing("Mobility", |ui| {
+                let sim = &sim_wrapper.sim;
+                let mobility = mobility_state.report.get_or_insert_with(|| sim.mobility());
+                ui.label(format!(
+                    "DOF {} (Jacobian rank {}), equation count {}, Kutzbach {} spatial / {} planar",
+                    mobility.numerical, mobility.jacobian_rank, mobility.equation_count,
+                    mobility.kutzbach_spatial, mobility.kutzbach_planar,
+                ));
+                for index in &mobility.redundant_constraints {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("  redundant #{} {}", index, sim.constraints[*index].type_name()),
+                    );
+                }
+                let free: Vec<&str> = mobility.under_constrained.iter()
+                    .filter_map(|id| sim.joints.get(*id).map(|joint| joint.name.as_str()))
+                    .collect();
+                ui.label(format!("free joints: {}", free.join(", ")));
+                for belt in sim.constraints.iter().filter_map(|c| c.as_any().downcast_ref::<BeltConstraint>()) {
+                    ui.label(format!("belt over {} pulleys, length {:.4}", belt.pulleys.len(), belt.length(sim)));
+                }
+            });
+
+            ui.collapsing("Interference", |ui| {
+                let sim = &sim_wrapper.sim;
+                if interference.report.pairs.is_empty() {
+                    ui.label("no capsules that can touch");
+                }
+                for pair in &interference.report.pairs {
+                    let names = format!("{} / {}", link_label(sim, pair.links.0), link_label(sim, pair.links.1));
+                    match pair.first_contact {
+                        Some(time) => ui.colored_label(
+                            egui::Color32::LIGHT_RED,
+                            format!("{} touched at t = {:.2}s, deepest {:.2e}", names, time, pair.min_clearance),
+                        ),
+                        None => ui.label(format!(
+                            "{} clearance {:.4} at t = {:.2}s", names, pair.min_clearance, pair.at_time,
+                        )),
+                    };
+                }
+                if ui.button("Clear").clicked() {
+                    interference.report = Default::default();
+                }
+            });
+
+            ui.collapsing("Statics", |ui| {
+                let sim = &sim_wrapper.sim;
+                ui.checkbox(&mut statics.show, "show reactions and link loads");
+                ui.add(egui::Slider::new(&mut statics.arrow_scale, 0.001..=1.0).logarithmic(true).text("arrow length per N"));
+                if !statics.show {
+                    return;
+                }
+                let report = &statics.report;
+                if report.unbalanced > 1e-3 {
+                    ui.colored_label(
+                        egui::Color32::LIGHT_RED,
+                        format!("not in equilibrium, {:.3} N unbalanced", report.unbalanced),
+                    );
+                }
+                if report.redundant_rows > 0 {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("{} redundant equations, their load split is the least norm one", report.redundant_rows),
+                    );
+                }
+                egui::Grid::new("joint_reactions").striped(true).show(ui, |ui| {
+                    ui.label("joint");
+                    ui.label("support");
+                    ui.label("bearing");
+                    ui.end_row();
+                    for reaction in &report.reactions {
+                        let Some(joint) = sim.joints.get(reaction.joint_id) else {
+                            continue;
+                        };
+                        ui.label(joint.name.as_str());
+                        ui.label(format!("{:.3}", reaction.support.length()));
+                        ui.label(format!("{:.3}", reaction.bearing));
+                        ui.end_row();
+                    }
+                });
+                egui::Grid::new("link_loads").striped(true).show(ui, |ui| {
+                    ui.label("link");
+                    ui.label("axial (+ tension)");
+                    ui.end_row();
+                    for (link_id, tension) in &report.link_loads {
+                        ui.label(link_label(sim, *link_id));
+                        ui.label(format!("{:.3}", tension));
+                        ui.end_row();
+                    }
+                });
+                let unlisted = sim.links.len() - report.link_loads.len();
+                if unlisted > 0 {
+                    ui.label(format!("{} bodies and links without a distance constraint not listed", unlisted));
+                }
+                for (index, load) in &report.driver_loads {
+                    let constraint = &sim.constraints[*index];
+                    let unit = if constraint.as_any().is::<LinearActuatorConstraint>() { "N" } else { "N m" };
+                    ui.label(format!("{} #{} input {:.3} {}", constraint.type_name(), index, load, unit));
+                }
+            });
+
+            ui.collapsing("Kinematics", |ui| {
+                let sim = &sim_wrapper.sim;
+                ui.checkbox(&mut kinematics.show, "show velocity and acceleration");
+                ui.add(egui::Slider::new(&mut kinematics.arrow_scale, 0.01..=2.0).logarithmic(true).text("arrow time (s)"));
+                if !kinematics.show {
+                    return;
+                }
+                let KinematicsState { report, peaks, .. } = &mut *kinematics;
+                egui::Grid::new("joint_kinematics").striped(true).show(ui, |ui| {
+                    ui.label("joint");
+                    ui.label("speed");
+                    ui.label("accel");
+                    ui.label("peak speed");
+                    ui.label("peak accel");
+                    ui.end_row();
+                    for motion in &report.joints {
+                        let Some(joint) = sim.joints.get(motion.joint_id) else {
+                            continue;
+                        };
+                        let (peak_speed, peak_acceleration) = peaks.joints.iter()
+                            .find(|(id, _, _)| *id == motion.joint_id)
+                            .map_or((0.0, 0.0), |(_, speed, acceleration)| (*speed, *acceleration));
+                        ui.label(joint.name.as_str());
+                        ui.label(format!("{:.3}", motion.velocity.length()));
+                        ui.label(format!("{:.3}", motion.acceleration.length()));
+                        ui.label(format!("{:.3}", peak_speed));
+                        ui.label(format!("{:.3}", peak_acceleration));
+                        ui.end_row();
+                    }
+                });
+                egui::Grid::new("link_kinematics").striped(true).show(ui, |ui| {
+                    ui.label("link");
+                    ui.label("angular velocity");
+                    ui.label("angular accel");
+                    ui.end_row();
+                    for motion in &report.links {
+                        ui.label(link_label(sim, motion.link_id));
+                        ui.label(format!("{:.3}", motion.angular_velocity.length()));
+                        ui.label(format!("{:.3}", motion.angular_acceleration.length()));
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Reset peaks").clicked() {
+                    *peaks = Default::default();
+                }
+            });
 
             if ui.button("Compile from ").clicked() {
 
@@ -192,6 +425,10 @@ fn ui_example_system(
                     Ok(new_sim) => {
                         println!("Successfully created simulation with {} joints", new_sim.joints.len());
                         sim_wrapper.sim = new_sim;
+                        interference.report = Default::default();
+                        kinematics.peaks = Default::default();
+                        singularity_state.active = None;
+                        singularity_state.baseline = Default::default();
                         render_sim(
                             sim_wrapper,
                             joint_query,
@@ -220,6 +457,9 @@ pub fn keybindings_ui(
     if text_state.other_speed_string.is_empty() {
         text_state.other_speed_string = bindings.iterations_per_time_step.to_string();
     }
+    if text_state.tolerance_string.is_empty() {
+        text_state.tolerance_string = bindings.solver_tolerance.to_string();
+    }
 
     egui::Window::new("Keybindings").show(contexts.ctx_mut(), |ui| {
         ui.label("Click a button, then press a new key.");
@@ -245,6 +485,7 @@ pub fn keybindings_ui(
         editable_binding!("Zoom In", zoom_in);
         editable_binding!("Zoom Out", zoom_out);
         editable_binding!("Shift", shift);
+        editable_binding!("Play / Pause", play_pause);
 
         ui.separator();
 
@@ -263,6 +504,19 @@ pub fn keybindings_ui(
                 }
             }
         });
+
+        ui.horizontal(|ui| {
+            ui.label("solver tolerance");
+
+            let response = ui.text_edit_singleline(&mut text_state.tolerance_string);
+            if response.changed() {
+                if let Ok(val) = text_state.tolerance_string.trim().parse::<f32>() {
+                    if val > 0.0 {
+                        bindings.solver_tolerance = val;
+                    }
+                }
+            }
+        });
     });
 
     if let Some(field) = listen.current {
@@ -277,6 +531,7 @@ pub fn keybindings_ui(
                 "zoom_in" => bindings.zoom_in = *key,
                 "zoom_out" => bindings.zoom_out = *key,
                 "shift" => bindings.shift = *key,
+                "play_pause" => bindings.play_pause = *key,
                 _ => {}
             }
             listen.current = None;
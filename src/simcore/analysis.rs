@@ -0,0 +1,365 @@
+use crate::simcore::types::*;
+use crate::simcore::solvers::linalg::*;
+
+const RANK_TOLERANCE: f64 = 1e-6;
+
+/// Degrees of freedom of a mechanism at its current pose
+#[derive(Debug, Clone, Default)]
+pub struct MobilityReport {
+    /// 3 per joint minus one per constraint equation, blind to redundancy
+    pub equation_count: i32,
+    /// Spatial Kutzbach count over links and joint types, 6 (n - 1) - sum(6 - f).
+    /// n counts the ground, which every fixed joint and every joint sliding against the world connects to.
+    /// Two joint links count their idle spin about the link line, the numerical count doesn't.
+    pub kutzbach_spatial: i32,
+    /// Planar Kutzbach count on the same graph, 3 (n - 1) - sum(3 - f)
+    pub kutzbach_planar: i32,
+    /// 3 per joint minus the rank of the constraint Jacobian
+    pub numerical: i32,
+    pub jacobian_rank: usize,
+    /// Constraints with at least one equation implied by the ones before it
+    pub redundant_constraints: Vec<usize>,
+    /// Joints that can still move to first order, either the intended motion or a missing constraint
+    pub under_constrained: Vec<JointId>,
+}
+
+impl MobilityReport {
+    /// Equations that counting alone can't see, equation_count and numerical disagree by this much
+    pub fn redundancy(&self) -> i32 {
+        self.numerical - self.equation_count
+    }
+}
+
+/// Freedom a joint type allows between two links (turning) and against the ground (turning and sliding)
+fn joint_freedom(joint: &Joint, spatial: bool) -> (i32, i32) {
+    let (turn, slide) = match joint.joint_type {
+        // a pinned point still lets its links turn about it
+        JointType::Fixed | JointType::Spherical { .. } => (3, 0),
+        JointType::Revolute { .. } => (1, 0),
+        JointType::Slider { .. } => (3, 1),
+        JointType::Universal { .. } => (2, 0),
+        JointType::Cylindrical { .. } => (1, 1),
+        JointType::Planar { .. } => (1, 2),
+    };
+    let slide = if joint.inv_mass > 0.0 { slide } else { 0 };
+    if spatial { (turn, turn + slide) } else { (1, 1 + slide) }
+}
+
+impl Simulation {
+    /// Kutzbach mobility, 6 (n - 1) - sum(6 - f) in space or 3 (n - 1) - sum(3 - f) in the plane.
+    /// The bodies are the links with a joint that can move plus one implicit ground, a link drawn
+    /// between fixed joints is part of that ground. A joint shared by k bodies is k - 1 pairs,
+    /// the pair with the ground gets the joint's sliding freedom as well.
+    pub fn kutzbach(&self, spatial: bool) -> i32 {
+        let body = if spatial { 6 } else { 3 };
+        let is_ground = |link: &LinkId| self.links.get(*link).is_none_or(|link| {
+            link.joints.iter().all(|id| self.joints.get(*id).is_none_or(|joint| joint.inv_mass == 0.0))
+        });
+
+        let moving = self.links.iter().filter(|(id, _)| !is_ground(id)).count() as i32;
+        let mut mobility = body * moving;
+        for (_, joint) in self.joints.iter() {
+            let (turn, grounded_freedom) = joint_freedom(joint, spatial);
+            let links = joint.connected_links.iter().filter(|id| !is_ground(id)).count() as i32;
+            let grounded = joint.inv_mass == 0.0 || grounded_freedom > turn
+                || joint.connected_links.iter().any(is_ground);
+            if grounded && links > 0 {
+                mobility -= body - grounded_freedom;
+            }
+            mobility -= (links - 1).max(0) * (body - turn);
+        }
+        mobility
+    }
+}
+
+impl Simulation {
+    pub fn mobility(&self) -> MobilityReport {
+        let jacobian = self.jacobian();
+        let coordinates = jacobian.column_count() as i32;
+
+        let equation_count = coordinates - jacobian.row_count() as i32;
+
+        let (basis, independent) = row_basis(&jacobian.matrix, RANK_TOLERANCE);
+        let rank = basis.len();
+
+        let mut redundant_constraints: Vec<usize> = independent.iter().enumerate()
+            .filter(|(_, independent)| !**independent)
+            .map(|(row, _)| jacobian.owners[row])
+            .collect();
+        redundant_constraints.dedup();
+
+        // Diagonal of the null space projector I - Q^T Q, nonzero where a coordinate is free
+        let under_constrained = jacobian.joints.iter().enumerate()
+            .filter(|(i, _)| {
+                (0..3).any(|axis| {
+                    let k = i * 3 + axis;
+                    let pinned: f64 = basis.iter().map(|q| q[k] * q[k]).sum();
+                    1.0 - pinned > RANK_TOLERANCE
+                })
+            })
+            .map(|(_, id)| *id)
+            .collect();
+
+        MobilityReport {
+            equation_count,
+            kutzbach_spatial: self.kutzbach(true),
+            kutzbach_planar: self.kutzbach(false),
+            numerical: coordinates - rank as i32,
+            jacobian_rank: rank,
+            redundant_constraints,
+            under_constrained,
+        }
+    }
+}
+
+// Singular values under this count towards the null space (redundant rows, the mechanism's own DOF)
+const STRUCTURAL_TOLERANCE: f64 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SingularityKind {
+    /// The free mechanism gains a DOF, links fold onto a line and the branch can flip
+    ChangePoint,
+    /// The inputs can no longer drive the mechanism, a follower lines up with its coupler
+    DeadPoint,
+    /// A joint stops moving while the rest of the mechanism moves, e.g. a rocker at its extreme
+    Toggle,
+}
+
+#[derive(Debug, Clone)]
+pub struct Singularity {
+    pub kind: SingularityKind,
+    pub constraints: Vec<usize>, // constraints taking part, index into Simulation::constraints
+    pub joints: Vec<JointId>,    // joints that lose (or gain) their motion
+    pub measure: f64,            // smallest singular value, or speed ratio for toggles, 0 = exactly singular
+}
+
+/// Null space dimension of the Jacobian at regular poses, free and with the inputs held.
+/// A singular pose only ever adds null directions, so the fewest seen so far is the baseline.
+/// Start a new one for every mechanism.
+#[derive(Debug, Clone, Default)]
+pub struct MobilityBaseline {
+    free: Option<usize>,
+    pinned: Option<(Vec<JointId>, usize)>,
+}
+
+impl MobilityBaseline {
+    fn free(&mut self, nullity: usize) -> usize {
+        let baseline = self.free.map_or(nullity, |seen| seen.min(nullity));
+        self.free = Some(baseline);
+        baseline
+    }
+
+    // Holding other joints gives a different null space, start over when the inputs change
+    fn pinned(&mut self, inputs: &[JointId], nullity: usize) -> usize {
+        let baseline = match &self.pinned {
+            Some((seen_inputs, seen)) if seen_inputs == inputs => nullity.min(*seen),
+            _ => nullity,
+        };
+        self.pinned = Some((inputs.to_vec(), baseline));
+        baseline
+    }
+}
+
+impl Simulation {
+    /// Check whether the pose is within `tolerance` of a singular configuration.
+    /// `inputs` are joints whose motion is imposed (driven or dragged), they are pinned
+    /// for the dead point check. `baseline` carries the regular null space dimension between calls.
+    pub fn singularity(&self, inputs: &[JointId], tolerance: f64, baseline: &mut MobilityBaseline) -> Option<Singularity> {
+        let jacobian = self.jacobian();
+        let columns = jacobian.column_count();
+        if columns == 0 {
+            return None;
+        }
+        let movable: Vec<bool> = jacobian.joints.iter()
+            .map(|id| self.joints.get(*id).is_some_and(|j| j.inv_mass > 0.0))
+            .collect();
+
+        // Free mechanism first
+        let eigen = symmetric_eigen(&gram(&jacobian.matrix, columns));
+        let free = baseline.free(nullity(&eigen.0));
+        if let Some(found) = near_singular(&jacobian.matrix, &jacobian.owners, &jacobian.joints, &movable, &eigen, free, tolerance) {
+            return Some(Singularity { kind: SingularityKind::ChangePoint, ..found });
+        }
+
+        // Then with the inputs held still
+        if !inputs.is_empty() {
+            let mut pinned = jacobian.matrix.clone();
+            let mut owners = jacobian.owners.clone();
+            for input in inputs {
+                if let Some(i) = jacobian.joints.iter().position(|id| id == input) {
+                    for axis in 0..3 {
+                        let mut row = vec![0.0; columns];
+                        row[i * 3 + axis] = 1.0;
+                        pinned.push(row);
+                        owners.push(usize::MAX);
+                    }
+                }
+            }
+            let held_eigen = symmetric_eigen(&gram(&pinned, columns));
+            let held = baseline.pinned(inputs, nullity(&held_eigen.0));
+            if let Some(found) = near_singular(&pinned, &owners, &jacobian.joints, &movable, &held_eigen, held, tolerance) {
+                return Some(Singularity { kind: SingularityKind::DeadPoint, ..found });
+            }
+        }
+
+        // Toggle, a moving joint that is nearly stationary in every free motion
+        let speeds: Vec<f64> = (0..jacobian.joints.len())
+            .map(|i| {
+                eigen.1.iter().take(free)
+                    .map(|v| (0..3).map(|axis| v[i * 3 + axis].powi(2)).sum::<f64>())
+                    .sum::<f64>()
+                    .sqrt()
+            })
+            .collect();
+        let fastest = speeds.iter().zip(movable.iter())
+            .filter(|(_, movable)| **movable)
+            .map(|(speed, _)| *speed)
+            .fold(0.0, f64::max);
+        if fastest <= STRUCTURAL_TOLERANCE {
+            return None;
+        }
+
+        let mut measure = f64::MAX;
+        let joints: Vec<JointId> = speeds.iter().enumerate()
+            .filter(|(i, speed)| {
+                let ratio = **speed / fastest;
+                movable[*i] && ratio > STRUCTURAL_TOLERANCE && ratio < tolerance
+            })
+            .map(|(i, speed)| {
+                measure = measure.min(speed / fastest);
+                jacobian.joints[i]
+            })
+            .collect();
+        if joints.is_empty() {
+            return None;
+        }
+
+        Some(Singularity {
+            kind: SingularityKind::Toggle,
+            constraints: Vec::new(),
+            joints,
+            measure,
+        })
+    }
+}
+
+// Singular values that count as zero, the null space dimension at this pose
+fn nullity(values: &[f64]) -> usize {
+    values.iter().filter(|value| value.max(0.0).sqrt() <= STRUCTURAL_TOLERANCE).count()
+}
+
+// Smallest singular value past the `baseline` null space, if it is under `tolerance`.
+// At an exactly singular pose this is the one that vanished.
+fn near_singular(
+    matrix: &Matrix,
+    owners: &[usize],
+    joints: &[JointId],
+    movable: &[bool],
+    (values, vectors): &(Vec<f64>, Matrix),
+    baseline: usize,
+    tolerance: f64,
+) -> Option<Singularity> {
+    let (sigma, v) = values.iter().zip(vectors.iter())
+        .map(|(value, v)| (value.max(0.0).sqrt(), v))
+        .nth(baseline)?;
+    if sigma >= tolerance {
+        return None;
+    }
+
+    // Joints that move along the vanishing direction
+    let block: Vec<f64> = (0..joints.len())
+        .map(|i| (0..3).map(|axis| v[i * 3 + axis].powi(2)).sum::<f64>().sqrt())
+        .collect();
+    let largest = block.iter().cloned().fold(0.0, f64::max);
+    let involved_joints = joints.iter().enumerate()
+        .filter(|(i, _)| movable[*i] && block[*i] > 0.25 * largest)
+        .map(|(_, id)| *id)
+        .collect();
+
+    // Rows that combine into the near dependency, left singular vector u = J v / sigma. At an
+    // exactly singular pose J v vanishes, u is then the extra null vector of J J^T instead
+    let u: Vec<f64> = if sigma > STRUCTURAL_TOLERANCE {
+        matrix.iter().map(|row| dot(row, v)).collect()
+    } else {
+        let rows: Matrix = matrix.iter().map(|a| matrix.iter().map(|b| dot(a, b)).collect()).collect();
+        let regular_rank = v.len().saturating_sub(baseline);
+        symmetric_eigen(&rows).1.into_iter().nth(matrix.len().saturating_sub(regular_rank))?
+    };
+    let largest = u.iter().map(|x| x.abs()).fold(0.0, f64::max);
+    let mut constraints: Vec<usize> = u.iter().enumerate()
+        .filter(|(r, x)| owners[*r] != usize::MAX && x.abs() > 0.25 * largest)
+        .map(|(r, _)| owners[r])
+        .collect();
+    constraints.dedup();
+
+    Some(Singularity {
+        kind: SingularityKind::ChangePoint,
+        constraints,
+        joints: involved_joints,
+        measure: sigma,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+    use glam::Vec3;
+
+    #[test]
+    fn change_point_found_at_the_exact_pose() {
+        let program = UgokuParser::parse_dsl("sim parallelogram {
+    joint a(0,0,0)
+    joint b(0,1,0)
+    joint c(2,1,0)
+    joint d(2,0,0)
+    distance(a,b,1)
+    distance(b,c,2)
+    distance(c,d,1)
+    fixed(a,d)
+    plane((a,b,c,d), Z)
+}").unwrap();
+        let mut sim = DslCompiler::compile_to_simulation(program).unwrap();
+        let mut baseline = MobilityBaseline::default();
+        assert!(sim.singularity(&[], 0.05, &mut baseline).is_none());
+
+        // folded flat, every link on the x axis
+        let ids: Vec<JointId> = sim.joints.iter().map(|(id, _)| id).collect();
+        sim.joints[ids[1]].position = Position::Vec3(Vec3::new(1.0, 0.0, 0.0));
+        sim.joints[ids[2]].position = Position::Vec3(Vec3::new(3.0, 0.0, 0.0));
+        let found = sim.singularity(&[], 0.05, &mut baseline).unwrap();
+        assert_eq!(found.kind, SingularityKind::ChangePoint);
+        assert!(found.measure < 1e-6);
+    }
+
+    #[test]
+    fn kutzbach_counts_the_ground_once() {
+        let build = |src: &str| DslCompiler::compile_to_simulation(UgokuParser::parse_dsl(src).unwrap()).unwrap();
+
+        // the drawn ground link between the fixed pivots is not a body of its own
+        let mobility = build(include_str!("../examples/crankrocker.ugoku")).mobility();
+        assert_eq!((mobility.kutzbach_planar, mobility.kutzbach_spatial), (1, 6));
+        // counting is blind to the drive, the Jacobian is not
+        assert_eq!(mobility.numerical, 0);
+
+        // the coupler's idle spin is counted in space
+        let mobility = build(include_str!("../examples/rssr.ugoku")).mobility();
+        assert_eq!(mobility.kutzbach_spatial, 2);
+
+        // slider crank, the slider ties the rod to the ground
+        let sim = build("sim slidercrank {
+    joint a(0,0,0)
+    joint b(1,0,0)
+    joint c(3,0,0) : slider(X)
+    link crank(a,b)
+    link rod(b,c)
+    distance(a,b,1)
+    distance(b,c,2)
+    fixed(a)
+}");
+        assert_eq!(sim.kutzbach(false), 1);
+        assert_eq!(sim.kutzbach(true), 4);
+        let mobility = sim.mobility();
+        assert_eq!(mobility.equation_count, 3 * 3 - 3 - 2 - 2);
+    }
+}
@@ -1,6 +1,8 @@
 use crate::simcore::types::*;
 use std::collections::HashMap;
 use glam::Vec3;
+use crate::simcore::solvers::curves::signed_area;
+use crate::simcore::solvers::solvers::{planar_frame, rigid_pairs};
 
 
 
@@ -20,11 +22,143 @@ pub fn apply_distance(
         joint_a: *joint_a_id,
         joint_b: *joint_b_id,
         target_distance: value,
+        xpbd: XpbdState::default(),
     }));
 
     Ok(())
 }
 
+pub fn apply_distance_range(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    a: &str,
+    b: &str,
+    (min, max): (f32, f32),
+) -> Result<(), String> {
+    let joint_a_id = joint_name_to_id.get(a)
+        .ok_or_else(|| format!("Joint '{}' not found", a))?;
+    let joint_b_id = joint_name_to_id.get(b)
+        .ok_or_else(|| format!("Joint '{}' not found", b))?;
+
+    sim.constraints.push(Box::new(DistanceRangeConstraint {
+        joint_a: *joint_a_id,
+        joint_b: *joint_b_id,
+        min_distance: min,
+        max_distance: max,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+pub fn apply_spring(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (a, b): (&str, &str),
+    rest_length: f32,
+    (stiffness, damping): (f32, f32),
+) -> Result<(), String> {
+    let joint_a_id = joint_name_to_id.get(a)
+        .ok_or_else(|| format!("Joint '{}' not found", a))?;
+    let joint_b_id = joint_name_to_id.get(b)
+        .ok_or_else(|| format!("Joint '{}' not found", b))?;
+    if stiffness < 0.0 || damping < 0.0 {
+        return Err(format!("Spring {}-{} needs a stiffness and damping of at least zero", a, b));
+    }
+
+    sim.forces.push(Box::new(LinearSpring {
+        joint_a: *joint_a_id,
+        joint_b: *joint_b_id,
+        rest_length,
+        stiffness,
+        damping,
+    }));
+
+    Ok(())
+}
+
+pub fn apply_torsion_spring(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (joint_a, pivot, joint_b): (&str, &str, &str),
+    rest_angle: f32,
+    (stiffness, damping): (f32, f32),
+    axis: Vec3,
+) -> Result<(), String> {
+    let find = |name: &str| joint_name_to_id.get(name)
+        .copied()
+        .ok_or_else(|| format!("Joint '{}' not found", name));
+    if stiffness < 0.0 || damping < 0.0 {
+        return Err(format!("Torsion spring at {} needs a stiffness and damping of at least zero", pivot));
+    }
+
+    sim.forces.push(Box::new(TorsionalSpring {
+        joint_a: find(joint_a)?,
+        pivot: find(pivot)?,
+        joint_b: find(joint_b)?,
+        axis: axis.normalize(),
+        rest_angle,
+        stiffness,
+        damping,
+    }));
+
+    Ok(())
+}
+
+pub fn apply_load(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    joint: &str,
+    force: Vec3,
+) -> Result<(), String> {
+    let joint_id = joint_name_to_id.get(joint)
+        .ok_or_else(|| format!("Joint '{}' not found", joint))?;
+
+    sim.forces.push(Box::new(PointLoad { joint_id: *joint_id, force }));
+
+    Ok(())
+}
+
+pub fn apply_torque(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (pivot, arm): (&str, &str),
+    torque: f32,
+    axis: Vec3,
+) -> Result<(), String> {
+    let pivot_id = joint_name_to_id.get(pivot)
+        .ok_or_else(|| format!("Pivot joint '{}' not found", pivot))?;
+    let arm_id = joint_name_to_id.get(arm)
+        .ok_or_else(|| format!("Joint '{}' not found", arm))?;
+
+    sim.forces.push(Box::new(AppliedTorque {
+        pivot: *pivot_id,
+        arm: *arm_id,
+        axis: axis.normalize(),
+        torque,
+    }));
+
+    Ok(())
+}
+
+pub fn apply_mass(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    joints: &[String],
+    mass: f32,
+) -> Result<(), String> {
+    if mass.is_nan() || mass <= 0.0 {
+        return Err(format!("Mass of {:?} must be above zero, use fixed to ground a joint", joints));
+    }
+    for joint_name in joints {
+        let joint_id = joint_name_to_id.get(joint_name)
+            .ok_or_else(|| format!("Joint '{}' not found", joint_name))?;
+        sim.joints[*joint_id].set_mass(mass);
+    }
+
+    Ok(())
+}
+
 pub fn apply_fixed(
     sim: &mut Simulation,
     joint_name_to_id: &HashMap<String, JointId>,
@@ -34,11 +168,16 @@ pub fn apply_fixed(
         let joint_id = joint_name_to_id.get(joint_name)
             .ok_or_else(|| format!("Joint '{}' not found", joint_name))?;
 
-        let target_position = sim.joints.get(*joint_id).unwrap().position;
+        // ground joints have infinite mass, nothing should ever pull them
+        let joint = sim.joints.get_mut(*joint_id).unwrap();
+        joint.inv_mass = 0.0;
+        joint.velocity = Vec3::ZERO;
+        let target_position = joint.position;
 
         sim.constraints.push(Box::new(FixedPositionConstraint {
             joint_id: *joint_id,
             target_position,
+            xpbd: XpbdState::default(),
         }));
     }
     Ok(())
@@ -61,6 +200,7 @@ pub fn apply_plane(
             joint_id: *joint_id,
             normal,
             plane_point,
+            xpbd: XpbdState::default(),
         }));
     }
 
@@ -85,7 +225,8 @@ pub fn apply_prismatic_link(
         sim.constraints.push(Box::new(PrismaticConstraintLink {
             joint_id: *joint_id,
             link_id: *link_id,
-            origin: origin,
+            origin,
+            xpbd: XpbdState::default(),
         }));
     }
 
@@ -101,6 +242,7 @@ pub fn apply_prismatic_vector(
     joints: &[String],
     axis: Vec3,
     origin: Vec3,
+    limits: Option<(f32, f32)>,
 ) -> Result<(), String> {
     for joint_name in joints {
         let joint_id = joint_name_to_id.get(joint_name)
@@ -110,6 +252,8 @@ pub fn apply_prismatic_vector(
             joint_id: *joint_id,
             axis: axis.normalize(),
             origin,
+            limits,
+            xpbd: XpbdState::default(),
         }));
     }
 
@@ -118,10 +262,10 @@ pub fn apply_prismatic_vector(
 pub fn apply_fixed_angle(
     sim: &mut Simulation,
     joint_name_to_id: &HashMap<String, JointId>,
-    joint_a: &str,
-    pivot: &str,
-    joint_b: &str,
+    (joint_a, pivot, joint_b): (&str, &str, &str),
     angle: f32,
+    drive: Option<MotionProfile>,
+    axis: Vec3,
 ) -> Result<(), String> {
     let joint_a_id = joint_name_to_id
         .get(joint_a)
@@ -139,36 +283,565 @@ pub fn apply_fixed_angle(
         joint_a_id: *joint_a_id,
         joint_b_id: *joint_b_id,
         pivot_joint_id: *pivot_id,
-
+        axis: axis.normalize(),
         target_angle: angle,
+        drive,
+        xpbd: XpbdState::default(),
     }));
 
     Ok(())
 }
 
+/// Hinge about `axis` through the pivot, angle zero is the current pose. With a link the axis is
+/// given in world space for the current pose and carried by the link from then on.
 pub fn apply_revolute(
     sim: &mut Simulation,
     joint_name_to_id: &HashMap<String, JointId>,
-    pivot_joint: &str,
-    moving_joint:  &str,
-    rest_direction: Vec3,
-    min_angle: f32,
-    max_angle: f32,
-
-){
+    link_name_to_id: &HashMap<String, LinkId>,
+    (pivot_joint, moving_joint): (&str, &str),
+    axis: Vec3,
+    (min_angle, max_angle): (f32, f32),
+    link: Option<&str>,
+) -> Result<(), String> {
     let pivot_joint_id = joint_name_to_id
         .get(pivot_joint)
-        .ok_or_else(|| format!("Pivot joint '{}' not found", pivot_joint)).unwrap();
+        .ok_or_else(|| format!("Pivot joint '{}' not found", pivot_joint))?;
     let moving_joint_id = joint_name_to_id
         .get(moving_joint)
-        .ok_or_else(|| format!("Moving joint '{}' not found", moving_joint)).unwrap();
+        .ok_or_else(|| format!("Moving joint '{}' not found", moving_joint))?;
 
-    sim.constraints.push(Box::new(RevoluteConstraint {
-        pivot_joint_id: *pivot_joint_id,
-        moving_joint_id: *moving_joint_id,
-        rest_direction, // Default direction, can be adjusted later
+    let frame = match link {
+        None => CarriedFrame::default(),
+        Some(link_name) => {
+            let link_id = link_name_to_id.get(link_name)
+                .ok_or_else(|| format!("Link '{}' not found", link_name))?;
+            let link_joints = &sim.links.get(*link_id).unwrap().joints;
+            CarriedFrame::on_link(sim, link_joints[0], link_joints[1])
+                .ok_or_else(|| format!("Link '{}' has no length", link_name))?
+        }
+    };
+
+    let revolute = revolute_constraint(sim, *pivot_joint_id, *moving_joint_id, axis, (min_angle, max_angle), frame)?;
+    sim.constraints.push(Box::new(revolute));
+
+    Ok(())
+}
+
+// Hinge with angle zero at the current pose
+fn revolute_constraint(
+    sim: &Simulation,
+    pivot_joint_id: JointId,
+    moving_joint_id: JointId,
+    axis: Vec3,
+    (min_angle, max_angle): (f32, f32),
+    frame: CarriedFrame,
+) -> Result<RevoluteConstraint, String> {
+    let (pivot, moving) = (&sim.joints[pivot_joint_id].name, &sim.joints[moving_joint_id].name);
+    if min_angle > max_angle {
+        return Err(format!("Revolute '{}'-'{}' has its min angle above its max", pivot, moving));
+    }
+
+    let axis = axis.normalize();
+    let arm = joint_offset(sim, pivot_joint_id, moving_joint_id);
+    let zero_direction = (arm - axis * axis.dot(arm)).normalize_or_zero();
+    if zero_direction == Vec3::ZERO {
+        return Err(format!("Joint '{}' lies on the hinge axis of '{}'", moving, pivot));
+    }
+
+    Ok(RevoluteConstraint {
+        pivot_joint_id,
+        moving_joint_id,
+        axis,
+        zero_direction,
+        offset: axis.dot(arm),
+        frame,
         min_angle,
         max_angle,
+        xpbd: XpbdState::default(),
+    })
+}
+
+/// Constraints that make a joint behave like its type. The first link at the joint carries the
+/// axes and every other link turns against it, a joint on a single link turns against the world.
+pub fn apply_joint_type(sim: &mut Simulation, joint_id: JointId) -> Result<(), String> {
+    let joint = &sim.joints[joint_id];
+    let (name, position, joint_type) = (joint.name.clone(), joint.position.as_vec3(), joint.joint_type.clone());
+
+    // the joint at the far end of each link, for bodies the first one that isn't this joint
+    let arms: Vec<JointId> = joint.connected_links.iter()
+        .filter_map(|link_id| sim.links[*link_id].joints.iter().copied().find(|id| *id != joint_id))
+        .collect();
+    let (frame, arms) = match arms.split_first() {
+        Some((base, rest)) if !rest.is_empty() => (
+            CarriedFrame::on_link(sim, joint_id, *base)
+                .ok_or_else(|| format!("Joint '{}' sits on top of its neighbour", name))?,
+            rest.to_vec(),
+        ),
+        _ => (CarriedFrame::default(), arms),
+    };
+
+    let free = (-std::f32::consts::PI, std::f32::consts::PI);
+    let mut constraints: Vec<Box<dyn Constraint>> = Vec::new();
+    match joint_type {
+        JointType::Fixed => {
+            // same as fixed(), ground joints never move
+            let joint = &mut sim.joints[joint_id];
+            joint.inv_mass = 0.0;
+            joint.velocity = Vec3::ZERO;
+            constraints.push(Box::new(FixedPositionConstraint {
+                joint_id,
+                target_position: Position::Vec3(position),
+                xpbd: XpbdState::default(),
+            }));
+        }
+        JointType::Revolute { axis, limits } => {
+            for arm in &arms {
+                constraints.push(Box::new(revolute_constraint(sim, joint_id, *arm, axis, limits.unwrap_or(free), frame)?));
+            }
+        }
+        JointType::Slider { axis, limits } => {
+            constraints.push(Box::new(PrismaticConstraintVector {
+                joint_id,
+                axis: axis.normalize(),
+                origin: position,
+                limits,
+                xpbd: XpbdState::default(),
+            }));
+        }
+        JointType::Spherical { cone } => {
+            if let Some(cone) = cone {
+                for arm in &arms {
+                    constraints.push(Box::new(ConeConstraint {
+                        pivot_joint_id: joint_id,
+                        moving_joint_id: *arm,
+                        rest_direction: joint_offset(sim, joint_id, *arm).normalize(),
+                        max_angle: cone,
+                        frame,
+                        xpbd: XpbdState::default(),
+                    }));
+                }
+            }
+        }
+        JointType::Universal { axis_a, axis_b, limit } => {
+            let axis_a = axis_a.normalize();
+            let axis_b = (axis_b - axis_a * axis_a.dot(axis_b)).normalize_or_zero();
+            if axis_b == Vec3::ZERO {
+                return Err(format!("Universal joint '{}' has parallel axes", name));
+            }
+            if let Some(limit) = limit {
+                for arm in &arms {
+                    let rest = UniversalConstraint::angles(axis_a, axis_b, joint_offset(sim, joint_id, *arm));
+                    constraints.push(Box::new(UniversalConstraint {
+                        pivot_joint_id: joint_id,
+                        moving_joint_id: *arm,
+                        axis_a,
+                        axis_b,
+                        rest,
+                        limit,
+                        frame,
+                        xpbd: XpbdState::default(),
+                    }));
+                }
+            }
+        }
+        JointType::Cylindrical { axis, limits } => {
+            constraints.push(Box::new(PrismaticConstraintVector {
+                joint_id,
+                axis: axis.normalize(),
+                origin: position,
+                limits,
+                xpbd: XpbdState::default(),
+            }));
+            for arm in &arms {
+                constraints.push(Box::new(revolute_constraint(sim, joint_id, *arm, axis, free, frame)?));
+            }
+        }
+        JointType::Planar { normal } => {
+            constraints.push(Box::new(PlaneConstraint {
+                joint_id,
+                normal: normal.normalize(),
+                plane_point: position,
+                xpbd: XpbdState::default(),
+            }));
+            for arm in &arms {
+                constraints.push(Box::new(revolute_constraint(sim, joint_id, *arm, normal, free, frame)?));
+            }
+        }
+    }
+    sim.constraints.extend(constraints);
+
+    Ok(())
+}
+
+pub fn apply_drive(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    pivot: &str,
+    moving: &str,
+    axis: Vec3,
+    profile: MotionProfile,
+) -> Result<(), String> {
+    let pivot_id = joint_name_to_id.get(pivot)
+        .ok_or_else(|| format!("Pivot joint '{}' not found", pivot))?;
+    let moving_id = joint_name_to_id.get(moving)
+        .ok_or_else(|| format!("Joint '{}' not found", moving))?;
+
+    // Angle zero is wherever the crank starts
+    let axis = axis.normalize();
+    let zero_direction = arm_direction(sim, *pivot_id, *moving_id, axis)
+        .ok_or_else(|| format!("Drive arm '{}' -> '{}' is parallel to its axis", pivot, moving))?;
+
+    sim.constraints.push(Box::new(CrankDriverConstraint {
+        pivot_joint_id: *pivot_id,
+        moving_joint_id: *moving_id,
+        axis,
+        zero_direction,
+        profile,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+pub fn apply_actuator(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    joint: &str,
+    axis: Vec3,
+    profile: MotionProfile,
+) -> Result<(), String> {
+    let joint_id = joint_name_to_id.get(joint)
+        .ok_or_else(|| format!("Joint '{}' not found", joint))?;
+
+    // Displacement zero is wherever the joint starts
+    let origin = sim.joints.get(*joint_id).unwrap().position.as_vec3();
+
+    sim.constraints.push(Box::new(LinearActuatorConstraint {
+        joint_id: *joint_id,
+        axis: axis.normalize(),
+        origin,
+        profile,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+pub fn apply_gear(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (pivot_a, arm_a): (&str, &str),
+    (pivot_b, arm_b): (&str, &str),
+    ratio: f32,
+    axis: Vec3,
+) -> Result<(), String> {
+    let id = |name: &str| joint_name_to_id.get(name)
+        .copied()
+        .ok_or_else(|| format!("Joint '{}' not found", name));
+    let (pivot_a_id, arm_a_id, pivot_b_id, arm_b_id) = (id(pivot_a)?, id(arm_a)?, id(pivot_b)?, id(arm_b)?);
+
+    let axis = axis.normalize();
+    let reference_a = arm_direction(sim, pivot_a_id, arm_a_id, axis)
+        .ok_or_else(|| format!("Gear arm '{}' -> '{}' is parallel to its axis", pivot_a, arm_a))?;
+    let reference_b = arm_direction(sim, pivot_b_id, arm_b_id, axis)
+        .ok_or_else(|| format!("Gear arm '{}' -> '{}' is parallel to its axis", pivot_b, arm_b))?;
+
+    sim.constraints.push(Box::new(GearConstraint {
+        pivot_a: pivot_a_id,
+        arm_a: arm_a_id,
+        pivot_b: pivot_b_id,
+        arm_b: arm_b_id,
+        axis,
+        ratio,
+        reference_a,
+        reference_b,
+        phase: 0.0,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+pub fn apply_rack_pinion(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (pivot, arm): (&str, &str),
+    rack: &str,
+    pitch_radius: f32,
+    direction: Vec3,
+    axis: Vec3,
+) -> Result<(), String> {
+    let id = |name: &str| joint_name_to_id.get(name)
+        .copied()
+        .ok_or_else(|| format!("Joint '{}' not found", name));
+    let (pivot_id, arm_id, rack_id) = (id(pivot)?, id(arm)?, id(rack)?);
+
+    let axis = axis.normalize();
+    let direction = direction.normalize();
+    let reference_arm = arm_direction(sim, pivot_id, arm_id, axis)
+        .ok_or_else(|| format!("Pinion arm '{}' -> '{}' is parallel to its axis", pivot, arm))?;
+    let reference_offset = direction.dot(joint_offset(sim, pivot_id, rack_id));
+
+    sim.constraints.push(Box::new(RackPinionConstraint {
+        pivot: pivot_id,
+        arm: arm_id,
+        rack: rack_id,
+        axis,
+        direction,
+        pitch_radius,
+        reference_arm,
+        reference_offset,
+        phase: 0.0,
+        xpbd: XpbdState::default(),
     }));
 
-}
\ No newline at end of file
+    Ok(())
+}
+
+pub fn apply_screw(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (pivot, arm): (&str, &str),
+    nut: &str,
+    lead: f32,
+    axis: Vec3,
+) -> Result<(), String> {
+    let id = |name: &str| joint_name_to_id.get(name)
+        .copied()
+        .ok_or_else(|| format!("Joint '{}' not found", name));
+    let (pivot_id, arm_id, nut_id) = (id(pivot)?, id(arm)?, id(nut)?);
+
+    let axis = axis.normalize();
+    let reference_arm = arm_direction(sim, pivot_id, arm_id, axis)
+        .ok_or_else(|| format!("Screw arm '{}' -> '{}' is parallel to its axis", pivot, arm))?;
+    let reference_offset = axis.dot(joint_offset(sim, pivot_id, nut_id));
+
+    sim.constraints.push(Box::new(ScrewConstraint {
+        pivot: pivot_id,
+        arm: arm_id,
+        nut: nut_id,
+        axis,
+        lead,
+        reference_arm,
+        reference_offset,
+        phase: 0.0,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+pub fn apply_belt(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    pulleys: &[(String, String, f32)],
+    crossed: bool,
+    axis: Vec3,
+) -> Result<(), String> {
+    if pulleys.len() < 2 {
+        return Err("Belt needs at least 2 pulleys".to_string());
+    }
+    let axis = axis.normalize();
+
+    let mut belt_pulleys = Vec::with_capacity(pulleys.len());
+    for (pivot, arm, radius) in pulleys {
+        let pivot_id = *joint_name_to_id.get(pivot)
+            .ok_or_else(|| format!("Joint '{}' not found", pivot))?;
+        let arm_id = *joint_name_to_id.get(arm)
+            .ok_or_else(|| format!("Joint '{}' not found", arm))?;
+        let reference_arm = arm_direction(sim, pivot_id, arm_id, axis)
+            .ok_or_else(|| format!("Pulley arm '{}' -> '{}' is parallel to its axis", pivot, arm))?;
+        belt_pulleys.push(Pulley { pivot: pivot_id, arm: arm_id, radius: *radius, reference_arm });
+    }
+
+    sim.constraints.push(Box::new(BeltConstraint {
+        phases: vec![0.0; belt_pulleys.len() - 1],
+        pulleys: belt_pulleys,
+        axis,
+        crossed,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+/// `curve` is given in world coordinates at the current pose, a curve on a link is moved
+/// into the link frame so it rides along from here on
+pub fn apply_point_on_curve(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    link_name_to_id: &HashMap<String, LinkId>,
+    joint: &str,
+    curve: PathCurve,
+    link: Option<&str>,
+    axis: Vec3,
+) -> Result<(), String> {
+    let joint_id = joint_name_to_id.get(joint)
+        .ok_or_else(|| format!("Joint '{}' not found", joint))?;
+    let axis = axis.normalize();
+
+    let (curve, link) = match link {
+        None => (curve, None),
+        Some(link_name) => {
+            let link_id = link_name_to_id.get(link_name)
+                .ok_or_else(|| format!("Link '{}' not found", link_name))?;
+            let link_joints = &sim.links.get(*link_id).unwrap().joints;
+            let (origin_id, arm_id) = (link_joints[0], link_joints[1]);
+            let (origin, x, y) = planar_frame(sim, origin_id, arm_id, axis)
+                .ok_or_else(|| format!("Link '{}' is parallel to the curve axis", link_name))?;
+            let local = curve.transformed(
+                |p| Vec3::new(x.dot(p - origin), y.dot(p - origin), axis.dot(p - origin)),
+                |v| Vec3::new(x.dot(v), y.dot(v), axis.dot(v)),
+            );
+            (local, Some((origin_id, arm_id)))
+        }
+    };
+
+    sim.constraints.push(Box::new(PointOnCurveConstraint {
+        joint_id: *joint_id,
+        curve,
+        link,
+        axis,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+/// `points` are (x, y) in the link frame, two for a straight slot, three (start, middle, end) for an arc
+pub fn apply_slot(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    link_name_to_id: &HashMap<String, LinkId>,
+    pin: &str,
+    link_name: &str,
+    points: &[(f32, f32)],
+    axis: Vec3,
+) -> Result<(), String> {
+    let pin_id = joint_name_to_id.get(pin)
+        .ok_or_else(|| format!("Joint '{}' not found", pin))?;
+    let link_id = link_name_to_id.get(link_name)
+        .ok_or_else(|| format!("Link '{}' not found", link_name))?;
+    let link_joints = &sim.links.get(*link_id).unwrap().joints;
+    let (origin, arm) = (link_joints[0], link_joints[1]);
+    let axis = axis.normalize();
+    arm_direction(sim, origin, arm, axis)
+        .ok_or_else(|| format!("Link '{}' is parallel to the slot axis", link_name))?;
+
+    let local: Vec<Vec3> = points.iter().map(|(x, y)| Vec3::new(*x, *y, 0.0)).collect();
+    let slot = match local.as_slice() {
+        [start, end] => PathCurve::Polyline { points: vec![*start, *end] },
+        [start, middle, end] => PathCurve::arc_through(*start, *middle, *end)
+            .ok_or_else(|| format!("Slot points on '{}' are collinear", link_name))?,
+        _ => return Err("Slot needs 2 points (straight) or 3 points (arc)".to_string()),
+    };
+
+    sim.constraints.push(Box::new(PinInSlotConstraint {
+        pin: *pin_id,
+        origin,
+        arm,
+        axis,
+        slot,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+/// Keep the current shape of `joints`, by shape matching or by the 3n - 6 distances of `rigid_pairs`
+pub fn apply_body(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    joints: &[String],
+    shape_matching: bool,
+) -> Result<(), String> {
+    if joints.len() < 3 {
+        return Err(format!("A body needs 3 or more joints, got {}", joints.len()));
+    }
+    let ids = joints.iter()
+        .map(|name| joint_name_to_id.get(name).copied().ok_or_else(|| format!("Joint '{}' not found", name)))
+        .collect::<Result<Vec<JointId>, String>>()?;
+    let rest: Vec<Vec3> = ids.iter().map(|id| sim.joints.get(*id).unwrap().position.as_vec3()).collect();
+
+    if shape_matching {
+        sim.constraints.push(Box::new(ShapeMatchConstraint {
+            joints: ids,
+            rest,
+            rotation: glam::Quat::IDENTITY,
+            xpbd: XpbdState::default(),
+        }));
+        return Ok(());
+    }
+
+    for (i, j) in rigid_pairs(&rest) {
+        sim.constraints.push(Box::new(DistanceConstraint {
+            joint_a: ids[i],
+            joint_b: ids[j],
+            target_distance: rest[i].distance(rest[j]),
+            xpbd: XpbdState::default(),
+        }));
+    }
+
+    Ok(())
+}
+
+/// Direction from pivot to arm joint in the plane normal to `axis`, None if the arm lies on the axis
+fn arm_direction(sim: &Simulation, pivot: JointId, arm: JointId, axis: Vec3) -> Option<Vec3> {
+    let arm = sim.joints.get(arm)?.position.as_vec3() - sim.joints.get(pivot)?.position.as_vec3();
+    let planar = (arm - axis * axis.dot(arm)).normalize_or_zero();
+    (planar != Vec3::ZERO).then_some(planar)
+}
+
+pub fn apply_cam(
+    sim: &mut Simulation,
+    joint_name_to_id: &HashMap<String, JointId>,
+    (pivot, arm): (&str, &str),
+    follower: &str,
+    mut profile: CamProfile,
+    roller_radius: f32,
+    axis: Vec3,
+) -> Result<(), String> {
+    let id = |name: &str| joint_name_to_id.get(name)
+        .copied()
+        .ok_or_else(|| format!("Joint '{}' not found", name));
+    let (pivot_id, arm_id, follower_id) = (id(pivot)?, id(arm)?, id(follower)?);
+
+    let axis = axis.normalize();
+    arm_direction(sim, pivot_id, arm_id, axis)
+        .ok_or_else(|| format!("Cam arm '{}' -> '{}' is parallel to its axis", pivot, arm))?;
+
+    // Profiles are evaluated assuming counter clockwise order and sorted lift angles
+    match &mut profile {
+        CamProfile::RadialLift { lift, .. } => {
+            for (angle, _) in lift.iter_mut() {
+                *angle = angle.rem_euclid(std::f32::consts::TAU);
+            }
+            lift.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        CamProfile::Spline { points } => {
+            if points.len() < 3 {
+                return Err("Cam spline needs at least 3 points".to_string());
+            }
+            if signed_area(points) < 0.0 {
+                points.reverse();
+            }
+        }
+        CamProfile::Polar { .. } => {}
+    }
+
+    sim.constraints.push(Box::new(CamConstraint {
+        pivot: pivot_id,
+        arm: arm_id,
+        follower: follower_id,
+        axis,
+        profile,
+        roller_radius,
+        xpbd: XpbdState::default(),
+    }));
+
+    Ok(())
+}
+
+fn joint_offset(sim: &Simulation, from: JointId, to: JointId) -> Vec3 {
+    sim.joints.get(to).unwrap().position.as_vec3() - sim.joints.get(from).unwrap().position.as_vec3()
+}
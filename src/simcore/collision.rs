@@ -0,0 +1,236 @@
+use glam::Vec3;
+use crate::simcore::types::*;
+
+// Pairs closer than this have touched
+pub const CONTACT_TOLERANCE: f32 = 1e-4;
+
+/// Point on a segment as its two end joints with their weights
+pub type SegmentPoint = [(JointId, f32); 2];
+
+/// Closest approach of two capsules
+#[derive(Debug, Clone)]
+pub struct Contact {
+    pub links: (LinkId, LinkId),
+    /// Gap between the surfaces, negative when the capsules overlap
+    pub clearance: f32,
+    /// Closest point on each link as the ends of its segment with their weights
+    pub points: (SegmentPoint, SegmentPoint),
+    /// Unit direction from the second link's closest point to the first's
+    pub normal: Vec3,
+}
+
+impl Contact {
+    pub fn point_a(&self, sim: &Simulation) -> Vec3 {
+        weighted_point(sim, &self.points.0)
+    }
+
+    pub fn point_b(&self, sim: &Simulation) -> Vec3 {
+        weighted_point(sim, &self.points.1)
+    }
+}
+
+fn weighted_point(sim: &Simulation, ends: &SegmentPoint) -> Vec3 {
+    ends.iter()
+        .map(|(id, weight)| sim.joints.get(*id).map_or(Vec3::ZERO, |j| j.position.as_vec3()) * *weight)
+        .sum()
+}
+
+/// Segments a link's capsule is swept along, one for a two joint link, the outline of a body
+pub fn link_segments(link: &Link) -> Vec<(JointId, JointId)> {
+    match link.joints.len() {
+        0 => Vec::new(),
+        1 => vec![(link.joints[0], link.joints[0])],
+        2 => vec![(link.joints[0], link.joints[1])],
+        n => (0..n).map(|i| (link.joints[i], link.joints[(i + 1) % n])).collect(),
+    }
+}
+
+/// Parameters (s, t) of the closest points on segments p0-p1 and q0-q1
+pub fn closest_on_segments(p0: Vec3, p1: Vec3, q0: Vec3, q1: Vec3) -> (f32, f32) {
+    let (d1, d2, r) = (p1 - p0, q1 - q0, p0 - q0);
+    let (a, e, f) = (d1.length_squared(), d2.length_squared(), d2.dot(r));
+    if a <= 1e-12 && e <= 1e-12 {
+        return (0.0, 0.0);
+    }
+    if a <= 1e-12 {
+        return (0.0, (f / e).clamp(0.0, 1.0));
+    }
+    let c = d1.dot(r);
+    if e <= 1e-12 {
+        return ((-c / a).clamp(0.0, 1.0), 0.0);
+    }
+    let b = d1.dot(d2);
+    let denominator = a * e - b * b;
+    // parallel segments have a whole range of closest points, take the middle of the overlap so
+    // a push lands on both ends instead of tipping the segment about one of them
+    let mut s = if denominator > 1e-12 {
+        ((b * f - c * e) / denominator).clamp(0.0, 1.0)
+    } else {
+        ((-c / a).clamp(0.0, 1.0) + ((b - c) / a).clamp(0.0, 1.0)) / 2.0
+    };
+    let mut t = (b * s + f) / e;
+    if t < 0.0 {
+        t = 0.0;
+        s = (-c / a).clamp(0.0, 1.0);
+    } else if t > 1.0 {
+        t = 1.0;
+        s = ((b - c) / a).clamp(0.0, 1.0);
+    }
+    (s, t)
+}
+
+impl Simulation {
+    /// Closest approach of every pair of capsules that can touch. Capsules on different layers
+    /// and links sharing a joint are never paired.
+    pub fn clearances(&self) -> Vec<Contact> {
+        let solids: Vec<(LinkId, &Link, Capsule)> = self.links.iter()
+            .filter_map(|(id, link)| link.collision.map(|capsule| (id, link, capsule)))
+            .collect();
+        let position = |id: JointId| self.joints.get(id).map_or(Vec3::ZERO, |j| j.position.as_vec3());
+
+        let mut contacts = Vec::new();
+        for (i, (id_a, link_a, capsule_a)) in solids.iter().enumerate() {
+            for (id_b, link_b, capsule_b) in &solids[i + 1..] {
+                if capsule_a.layer != capsule_b.layer || link_a.joints.iter().any(|j| link_b.joints.contains(j)) {
+                    continue;
+                }
+                let mut closest: Option<Contact> = None;
+                for (a0, a1) in link_segments(link_a) {
+                    for (b0, b1) in link_segments(link_b) {
+                        let (p0, p1, q0, q1) = (position(a0), position(a1), position(b0), position(b1));
+                        let (s, t) = closest_on_segments(p0, p1, q0, q1);
+                        let offset = p0.lerp(p1, s) - q0.lerp(q1, t);
+                        let clearance = offset.length() - capsule_a.radius - capsule_b.radius;
+                        if closest.as_ref().is_some_and(|c| c.clearance <= clearance) {
+                            continue;
+                        }
+                        // touching centre lines have no direction of their own, push apart sideways
+                        let normal = match offset.try_normalize() {
+                            Some(normal) => normal,
+                            None => (p1 - p0).cross(q1 - q0).try_normalize()
+                                .unwrap_or_else(|| (p1 - p0).any_orthonormal_vector()),
+                        };
+                        closest = Some(Contact {
+                            links: (*id_a, *id_b),
+                            clearance,
+                            points: ([(a0, 1.0 - s), (a1, s)], [(b0, 1.0 - t), (b1, t)]),
+                            normal,
+                        });
+                    }
+                }
+                contacts.extend(closest);
+            }
+        }
+        contacts
+    }
+
+    /// Run the motion for `duration` and note how close each capsule pair came
+    pub fn interference_sweep(&mut self, duration: f32, dt: f32, iterations: usize, tolerance: f32) -> InterferenceReport {
+        let mut report = InterferenceReport::default();
+        report.record(self);
+        let steps = (duration / dt).ceil() as usize;
+        for _ in 0..steps {
+            self.step(dt, iterations, tolerance);
+            report.record(self);
+        }
+        report
+    }
+}
+
+/// Closest approach of one capsule pair over a motion
+#[derive(Debug, Clone)]
+pub struct Interference {
+    pub links: (LinkId, LinkId),
+    pub min_clearance: f32,
+    /// Time of the closest approach
+    pub at_time: f32,
+    /// First time the pair touched
+    pub first_contact: Option<f32>,
+}
+
+impl Interference {
+    pub fn touched(&self) -> bool {
+        self.first_contact.is_some()
+    }
+}
+
+/// Which link pairs came into contact during a motion, and how close the others got
+#[derive(Debug, Clone, Default)]
+pub struct InterferenceReport {
+    pub pairs: Vec<Interference>,
+}
+
+impl InterferenceReport {
+    pub fn record(&mut self, sim: &Simulation) {
+        self.record_contacts(&sim.clearances(), sim.time);
+    }
+
+    /// Fold in contacts already worked out at `time`
+    pub fn record_contacts(&mut self, contacts: &[Contact], time: f32) {
+        for contact in contacts {
+            let touching = contact.clearance <= CONTACT_TOLERANCE;
+            match self.pairs.iter_mut().find(|pair| pair.links == contact.links) {
+                Some(pair) => {
+                    if contact.clearance < pair.min_clearance {
+                        pair.min_clearance = contact.clearance;
+                        pair.at_time = time;
+                    }
+                    if touching && pair.first_contact.is_none() {
+                        pair.first_contact = Some(time);
+                    }
+                }
+                None => self.pairs.push(Interference {
+                    links: contact.links,
+                    min_clearance: contact.clearance,
+                    at_time: time,
+                    first_contact: touching.then_some(time),
+                }),
+            }
+        }
+    }
+
+    pub fn interfering(&self) -> impl Iterator<Item = &Interference> {
+        self.pairs.iter().filter(|pair| pair.touched())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+    use crate::simcore::solvers::solver_backends;
+
+    #[test]
+    fn capsules_stop_at_touching() {
+        let src = "sim guard {
+    joint p(0,1,0)
+    joint q(2,1,0)
+    joint a(0,0,0)
+    joint b(2,0,0)
+    link guard(p,q)
+    link bar(a,b)
+    distance(a,b,2)
+    fixed(p,q)
+    capsule(guard, 0.1)
+    capsule(bar, 0.1)
+}";
+        for solver in solver_backends() {
+            let mut sim = DslCompiler::compile_to_simulation(UgokuParser::parse_dsl(src).unwrap()).unwrap();
+            sim.solver = solver;
+            let before = sim.clearances();
+            assert_eq!(before.len(), 1);
+            assert!((before[0].clearance - 0.8).abs() < 1e-5);
+            // parallel, the contact sits midway so both ends of the bar take the push
+            assert_eq!(before[0].points.1.map(|(_, weight)| weight), [0.5, 0.5]);
+
+            // the bar is thrown at the guard and comes to rest against it
+            for (_, joint) in sim.joints.iter_mut().filter(|(_, joint)| joint.inv_mass > 0.0) {
+                joint.velocity = Vec3::new(0.0, 2.0, 0.0);
+            }
+            let report = sim.interference_sweep(1.0, 1.0 / 60.0, 40, 1e-6);
+            let pair = &report.pairs[0];
+            assert!(pair.touched(), "{}", sim.solver.name());
+            assert!(pair.min_clearance > -1e-3, "{} {}", sim.solver.name(), pair.min_clearance);
+        }
+    }
+}
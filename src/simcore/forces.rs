@@ -0,0 +1,244 @@
+use glam::Vec3;
+use std::any::Any;
+use crate::simcore::types::*;
+use crate::simcore::solvers::solvers::wrap_angle;
+
+impl Simulation {
+    /// Sum of every force element's pull on each joint, in element order
+    pub fn element_forces(&self) -> Vec<(JointId, Vec3)> {
+        let mut total: Vec<(JointId, Vec3)> = Vec::new();
+        for element in &self.forces {
+            for (joint_id, force) in element.forces(self) {
+                match total.iter_mut().find(|(id, _)| *id == joint_id) {
+                    Some((_, sum)) => *sum += force,
+                    None => total.push((joint_id, force)),
+                }
+            }
+        }
+        total
+    }
+
+    /// Energy held in the springs and the height of the masses under gravity
+    pub fn stored_energy(&self) -> f32 {
+        self.forces.iter().map(|element| element.energy(self)).sum()
+    }
+}
+
+impl LinearSpring {
+    /// Current (length, unit direction from A to B)
+    pub fn measure(&self, sim: &Simulation) -> Option<(f32, Vec3)> {
+        let a = sim.joints.get(self.joint_a)?.position.as_vec3();
+        let b = sim.joints.get(self.joint_b)?.position.as_vec3();
+        let length = a.distance(b);
+        (length > 1e-6).then(|| (length, (b - a) / length))
+    }
+
+    /// Tension along the spring, positive when it pulls the joints together
+    pub fn tension(&self, sim: &Simulation) -> f32 {
+        let Some((length, direction)) = self.measure(sim) else {
+            return 0.0;
+        };
+        let velocity = |id: JointId| sim.joints.get(id).map_or(Vec3::ZERO, |j| j.velocity);
+        let stretch_rate = direction.dot(velocity(self.joint_b) - velocity(self.joint_a));
+        self.stiffness * (length - self.rest_length) + self.damping * stretch_rate
+    }
+}
+
+impl ForceElement for LinearSpring {
+    fn forces(&self, sim: &Simulation) -> Vec<(JointId, Vec3)> {
+        let Some((_, direction)) = self.measure(sim) else {
+            return Vec::new();
+        };
+        let pull = direction * self.tension(sim);
+        vec![(self.joint_a, pull), (self.joint_b, -pull)]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn energy(&self, sim: &Simulation) -> f32 {
+        self.measure(sim).map_or(0.0, |(length, _)| 0.5 * self.stiffness * (length - self.rest_length).powi(2))
+    }
+}
+
+impl TorsionalSpring {
+    /// Current signed angle from arm A to arm B with d(angle)/dx for arm A, the pivot and arm B
+    pub fn measure(&self, sim: &Simulation) -> Option<(f32, [Vec3; 3])> {
+        let pivot = sim.joints.get(self.pivot)?.position.as_vec3();
+        let axis = self.axis.normalize();
+        let planar = |id: JointId| -> Option<Vec3> {
+            let arm = sim.joints.get(id)?.position.as_vec3() - pivot;
+            let planar = arm - axis * axis.dot(arm);
+            (planar.length_squared() > 1e-12).then_some(planar)
+        };
+        let (arm_a, arm_b) = (planar(self.joint_a)?, planar(self.joint_b)?);
+        let angle = axis.dot(arm_a.cross(arm_b)).atan2(arm_a.dot(arm_b));
+        let gradient_a = -axis.cross(arm_a) / arm_a.length_squared();
+        let gradient_b = axis.cross(arm_b) / arm_b.length_squared();
+        Some((angle, [gradient_a, -(gradient_a + gradient_b), gradient_b]))
+    }
+
+    // Wrapped to [-pi, pi] so the spring never winds up through the half turn
+    fn deflection(&self, angle: f32) -> f32 {
+        wrap_angle(angle - self.rest_angle)
+    }
+
+    /// Torque the spring resists with, positive when it turns arm B back towards arm A
+    pub fn torque(&self, sim: &Simulation) -> f32 {
+        let Some((angle, gradients)) = self.measure(sim) else {
+            return 0.0;
+        };
+        let velocity = |id: JointId| sim.joints.get(id).map_or(Vec3::ZERO, |j| j.velocity);
+        let angular_rate: f32 = [self.joint_a, self.pivot, self.joint_b].iter().zip(gradients)
+            .map(|(id, gradient)| gradient.dot(velocity(*id)))
+            .sum();
+        self.stiffness * self.deflection(angle) + self.damping * angular_rate
+    }
+}
+
+impl ForceElement for TorsionalSpring {
+    // a torque T on the angle is the force -T * d(angle)/dx on each joint
+    fn forces(&self, sim: &Simulation) -> Vec<(JointId, Vec3)> {
+        let Some((_, gradients)) = self.measure(sim) else {
+            return Vec::new();
+        };
+        let torque = self.torque(sim);
+        [self.joint_a, self.pivot, self.joint_b].into_iter().zip(gradients)
+            .map(|(id, gradient)| (id, -gradient * torque))
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn energy(&self, sim: &Simulation) -> f32 {
+        self.measure(sim).map_or(0.0, |(angle, _)| 0.5 * self.stiffness * self.deflection(angle).powi(2))
+    }
+}
+
+impl ForceElement for Gravity {
+    fn forces(&self, sim: &Simulation) -> Vec<(JointId, Vec3)> {
+        sim.joints.iter()
+            .filter(|(_, joint)| joint.inv_mass > 0.0)
+            .map(|(id, joint)| (id, self.acceleration * joint.mass()))
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    // measured from the world origin, only differences mean anything
+    fn energy(&self, sim: &Simulation) -> f32 {
+        sim.joints.iter()
+            .filter(|(_, joint)| joint.inv_mass > 0.0)
+            .map(|(_, joint)| -joint.mass() * self.acceleration.dot(joint.position.as_vec3()))
+            .sum()
+    }
+}
+
+impl ForceElement for PointLoad {
+    fn forces(&self, _sim: &Simulation) -> Vec<(JointId, Vec3)> {
+        vec![(self.joint_id, self.force)]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ForceElement for AppliedTorque {
+    // the force T * d(angle)/d(arm) on the arm, the pivot takes the opposite
+    fn forces(&self, sim: &Simulation) -> Vec<(JointId, Vec3)> {
+        let (Some(pivot), Some(arm)) = (sim.joints.get(self.pivot), sim.joints.get(self.arm)) else {
+            return Vec::new();
+        };
+        let axis = self.axis.normalize();
+        let offset = arm.position.as_vec3() - pivot.position.as_vec3();
+        let planar = offset - axis * axis.dot(offset);
+        if planar.length_squared() < 1e-12 {
+            return Vec::new();
+        }
+        let force = axis.cross(planar) / planar.length_squared() * self.torque;
+        vec![(self.arm, force), (self.pivot, -force)]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+
+    fn build(src: &str) -> Simulation {
+        DslCompiler::compile_to_simulation(UgokuParser::parse_dsl(src).unwrap()).unwrap()
+    }
+
+    fn position(sim: &Simulation, name: &str) -> Vec3 {
+        sim.joints.iter().find(|(_, joint)| joint.name == name).unwrap().1.position.as_vec3()
+    }
+
+    fn run(sim: &mut Simulation, seconds: f32) {
+        for _ in 0..(seconds * 60.0).round() as usize {
+            sim.step(1.0 / 60.0, 40, 1e-6);
+        }
+    }
+
+    #[test]
+    fn springs_pull_towards_their_rest_length_and_settle() {
+        let sim = build("sim s { joint a(0,0,0) joint b(2,0,0) spring(a,b,1,10) }");
+        let forces = sim.element_forces();
+        assert_eq!(forces.iter().map(|(_, force)| *force).collect::<Vec<_>>(), [Vec3::X * 10.0, Vec3::X * -10.0]);
+        assert!((sim.stored_energy() - 5.0).abs() < 1e-5);
+
+        let mut sim = build("sim s { joint a(0,0,0) joint b(2,0,0) fixed(a) spring(a,b,1,10,2) }");
+        run(&mut sim, 10.0);
+        assert!(position(&sim, "b").distance(Vec3::X) < 1e-3, "{:?}", position(&sim, "b"));
+
+        // a torsion spring swings its arm back round to the rest angle
+        let mut sim = build("sim s {
+    joint g(1,0,0)
+    joint a(0,0,0)
+    joint b(0,1,0)
+    fixed(g,a)
+    distance(a,b,1)
+    plane((b), Z)
+    torsion_spring(g,a,b,45deg,5,2)
+}");
+        run(&mut sim, 10.0);
+        let rest = Vec3::new(1.0, 1.0, 0.0).normalize();
+        assert!(position(&sim, "b").distance(rest) < 1e-3, "{:?}", position(&sim, "b"));
+    }
+
+    #[test]
+    fn gravity_loads_and_torques_reach_their_joints() {
+        let sim = build("sim s {
+    joint a(0,0,0)
+    joint b(1,0,0)
+    fixed(a)
+    mass(b, 2)
+    gravity(10)
+    load(b, (1,0,0))
+    torque(a,b,3)
+}");
+        let force = |name: &str| sim.element_forces().into_iter()
+            .find(|(id, _)| sim.joints[*id].name == name)
+            .map_or(Vec3::ZERO, |(_, force)| force);
+        // the fixed pivot feels no weight, only the torque's reaction
+        assert!(force("a").distance(Vec3::new(0.0, -3.0, 0.0)) < 1e-5, "{:?}", force("a"));
+        assert!(force("b").distance(Vec3::new(1.0, -20.0 + 3.0, 0.0)) < 1e-5, "{:?}", force("b"));
+
+        // a free joint falls at g whatever its mass
+        let mut sim = build("sim s { joint p(0,0,0) mass(p, 5) gravity((0,0,-2)) }");
+        sim.damping = 0.0;
+        run(&mut sim, 1.0);
+        let velocity = sim.joints.iter().next().unwrap().1.velocity;
+        assert!(velocity.distance(Vec3::new(0.0, 0.0, -2.0)) < 1e-4, "{velocity:?}");
+        assert!((position(&sim, "p").z + 1.0).abs() < 0.05, "{:?}", position(&sim, "p"));
+    }
+}
@@ -0,0 +1,200 @@
+use glam::{Mat3, Vec3};
+use crate::simcore::types::*;
+use crate::simcore::solvers::linalg::*;
+
+// Step for the finite differences in time and along the motion
+const DIFFERENCE_STEP: f32 = 1e-2;
+
+#[derive(Debug, Clone)]
+pub struct JointMotion {
+    pub joint_id: JointId,
+    pub velocity: Vec3,
+    pub acceleration: Vec3,
+}
+
+/// Rotation of a link, about the link's own axis for two joint links this is always zero
+#[derive(Debug, Clone)]
+pub struct LinkMotion {
+    pub link_id: LinkId,
+    pub angular_velocity: Vec3,
+    pub angular_acceleration: Vec3,
+}
+
+/// How fast everything moves at one pose of the driven motion
+#[derive(Debug, Clone, Default)]
+pub struct KinematicsReport {
+    pub joints: Vec<JointMotion>,
+    pub links: Vec<LinkMotion>,
+}
+
+impl KinematicsReport {
+    pub fn joint(&self, joint_id: JointId) -> Option<&JointMotion> {
+        self.joints.iter().find(|motion| motion.joint_id == joint_id)
+    }
+}
+
+impl Simulation {
+    /// Velocity and acceleration of every joint and link imposed by the drivers at the current
+    /// pose and time. J v = -dC/dt and J a = -d2C/ds2 along (x + s v, t + s), with the time
+    /// derivatives taken by central differences. Motion the drivers don't determine is left at
+    /// zero. The rows are evaluated on a copy of the joints, the simulation itself is untouched.
+    pub fn kinematics(&self) -> KinematicsReport {
+        let jacobian = self.jacobian();
+        let j = &jacobian.matrix;
+        let h = DIFFERENCE_STEP;
+        // rows only read the joints, links and clock
+        let mut probe = Simulation {
+            joints: self.joints.clone(),
+            links: self.links.clone(),
+            time: self.time,
+            ..Default::default()
+        };
+        let base = self.row_values(&mut probe, &[], 0.0);
+        let mut sample = |offsets: &[(JointId, Vec3)], time_offset: f32| -> Vec<f64> {
+            // a constraint whose rows come and go between samples (a limit being hit) reads as unchanged
+            self.row_values(&mut probe, offsets, time_offset).into_iter().zip(&base)
+                .flat_map(|(values, base)| if values.len() == base.len() { values } else { base.clone() })
+                .collect()
+        };
+
+        // velocity
+        let ahead = sample(&[], h);
+        let behind = sample(&[], -h);
+        let base: Vec<f64> = base.iter().flatten().copied().collect();
+        let rhs: Vec<f64> = (0..base.len()).map(|r| -(ahead[r] - behind[r]) / (2.0 * h as f64)).collect();
+        let velocities = transpose_times(j, &row_space_solve(j, &rhs));
+        let velocities = jacobian.per_joint(&velocities);
+
+        // acceleration, from the curvature of C along the velocity
+        let step: Vec<(JointId, Vec3)> = velocities.iter().map(|(id, v)| (*id, *v * h)).collect();
+        let ahead = sample(&step, h);
+        let back: Vec<(JointId, Vec3)> = step.iter().map(|(id, d)| (*id, -*d)).collect();
+        let behind = sample(&back, -h);
+        let rhs: Vec<f64> = (0..base.len())
+            .map(|r| -(ahead[r] - 2.0 * base[r] + behind[r]) / (h as f64 * h as f64))
+            .collect();
+        let accelerations = jacobian.per_joint(&transpose_times(j, &row_space_solve(j, &rhs)));
+
+        let joints: Vec<JointMotion> = velocities.into_iter().zip(accelerations)
+            .map(|((joint_id, velocity), (_, acceleration))| JointMotion { joint_id, velocity, acceleration })
+            .collect();
+        let links = self.links.iter()
+            .filter_map(|(link_id, link)| link_motion(self, &joints, link_id, link))
+            .collect();
+        KinematicsReport { joints, links }
+    }
+
+    // Row values of each constraint on `probe`, a copy of this pose with the joints offset and the clock shifted
+    fn row_values(&self, probe: &mut Simulation, offsets: &[(JointId, Vec3)], time_offset: f32) -> Vec<Vec<f64>> {
+        for (id, joint) in probe.joints.iter_mut() {
+            joint.position = self.joints[id].position;
+        }
+        for (id, offset) in offsets {
+            if let Some(joint) = probe.joints.get_mut(*id) {
+                joint.position = Position::Vec3(joint.position.as_vec3() + *offset);
+            }
+        }
+        probe.time = self.time + time_offset;
+
+        self.constraints.iter()
+            .map(|constraint| constraint.rows(probe).iter().map(|row| row.value as f64).collect())
+            .collect()
+    }
+}
+
+/// v = J^T y
+fn transpose_times(j: &Matrix, y: &[f64]) -> Vec<f64> {
+    let columns = j.first().map_or(0, |row| row.len());
+    (0..columns).map(|k| j.iter().zip(y).map(|(row, y)| row[k] * y).sum()).collect()
+}
+
+// Rigid motion fitted to the link's joints, v_i = v_c + w x r_i and a_i = a_c + alpha x r_i + w x (w x r_i)
+fn link_motion(sim: &Simulation, joints: &[JointMotion], link_id: LinkId, link: &Link) -> Option<LinkMotion> {
+    let samples: Vec<(Vec3, Vec3, Vec3)> = link.joints.iter()
+        .map(|id| {
+            let motion = joints.iter().find(|m| m.joint_id == *id)?;
+            Some((sim.joints.get(*id)?.position.as_vec3(), motion.velocity, motion.acceleration))
+        })
+        .collect::<Option<_>>()?;
+    if samples.len() < 2 {
+        return None;
+    }
+    let count = samples.len() as f32;
+    let centre = samples.iter().map(|s| s.0).sum::<Vec3>() / count;
+    let mean_velocity = samples.iter().map(|s| s.1).sum::<Vec3>() / count;
+    let mean_acceleration = samples.iter().map(|s| s.2).sum::<Vec3>() / count;
+
+    // sum r x (w x r) = M w with M = sum |r|^2 I - r r^T, singular about the axis of a two joint link
+    let inertia = samples.iter()
+        .map(|(p, _, _)| {
+            let r = *p - centre;
+            Mat3::from_diagonal(Vec3::splat(r.length_squared())) - outer(r, r)
+        })
+        .fold(Mat3::ZERO, |sum, m| sum + m);
+    let solve = |moment: Vec3| -> Vec3 {
+        if samples.len() > 2 && inertia.determinant().abs() > 1e-9 {
+            inertia.inverse() * moment
+        } else {
+            moment / (inertia.col(0).x + inertia.col(1).y + inertia.col(2).z).max(1e-12) * 2.0
+        }
+    };
+
+    let angular_velocity = solve(samples.iter()
+        .map(|(p, v, _)| (*p - centre).cross(*v - mean_velocity))
+        .sum());
+    let angular_acceleration = solve(samples.iter()
+        .map(|(p, _, a)| {
+            let r = *p - centre;
+            r.cross(*a - mean_acceleration - angular_velocity.cross(angular_velocity.cross(r)))
+        })
+        .sum());
+    Some(LinkMotion { link_id, angular_velocity, angular_acceleration })
+}
+
+fn outer(a: Vec3, b: Vec3) -> Mat3 {
+    Mat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+/// Fastest each joint moved over a motion, for sizing motors and acceleration limits
+#[derive(Debug, Clone, Default)]
+pub struct KinematicsPeaks {
+    pub joints: Vec<(JointId, f32, f32)>, // (joint, peak speed, peak acceleration)
+}
+
+impl KinematicsPeaks {
+    pub fn record(&mut self, report: &KinematicsReport) {
+        for motion in &report.joints {
+            let (speed, acceleration) = (motion.velocity.length(), motion.acceleration.length());
+            match self.joints.iter_mut().find(|(id, _, _)| *id == motion.joint_id) {
+                Some((_, peak_speed, peak_acceleration)) => {
+                    *peak_speed = peak_speed.max(speed);
+                    *peak_acceleration = peak_acceleration.max(acceleration);
+                }
+                None => self.joints.push((motion.joint_id, speed, acceleration)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+
+    #[test]
+    fn crank_pin_runs_round_at_the_drive_speed() {
+        let program = UgokuParser::parse_dsl(include_str!("../examples/crankrocker.ugoku")).unwrap();
+        let sim = DslCompiler::compile_to_simulation(program).unwrap();
+
+        let report = sim.kinematics();
+        let b = sim.joints.iter().nth(1).unwrap().0;
+        let motion = report.joint(b).unwrap();
+        let omega = 90f32.to_radians();
+        // tangential at 1 m from the pivot, centripetal back towards it
+        assert!(motion.velocity.distance(Vec3::new(0.0, omega, 0.0)) < 1e-2, "{:?}", motion.velocity);
+        assert!(motion.acceleration.distance(Vec3::new(-omega * omega, 0.0, 0.0)) < 5e-2, "{:?}", motion.acceleration);
+
+        let crank = report.links.iter().find(|link| sim.links[link.link_id].joints == [sim.joints.iter().next().unwrap().0, b]).unwrap();
+        assert!((crank.angular_velocity.z - omega).abs() < 1e-2);
+    }
+}
@@ -1,3 +1,8 @@
 pub mod types;
 pub mod solvers;
-pub mod bindings;
\ No newline at end of file
+pub mod bindings;
+pub mod analysis;
+pub mod collision;
+pub mod forces;
+pub mod statics;
+pub mod kinematics;
\ No newline at end of file
@@ -0,0 +1,256 @@
+// Curve helpers for cam outlines and guide paths.
+// Curves are parametrized by u in [0, 1], closed ones wrap around.
+
+use crate::simcore::types::*;
+use glam::{Vec2, Vec3};
+use std::f32::consts::TAU;
+use std::ops::{Add, Mul, Sub};
+
+/// Uniform Catmull-Rom between p1 and p2, t in [0, 1]
+pub fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Parameter minimizing `distance` along a curve, coarse sampling then a local refine.
+/// Closed curves wrap around, open ones are clamped to [0, 1].
+pub fn closest_parameter(distance: impl Fn(f32) -> f32, samples: usize, closed: bool) -> f32 {
+    let samples = samples.max(8);
+    let step = 1.0 / samples as f32;
+    let wrap = |u: f32| if closed { u.rem_euclid(1.0) } else { u.clamp(0.0, 1.0) };
+    let distance = |u: f32| distance(wrap(u));
+
+    let mut best = 0.0;
+    let mut best_distance = f32::MAX;
+    let last = if closed { samples - 1 } else { samples };
+    for i in 0..=last {
+        let u = i as f32 * step;
+        let d = distance(u);
+        if d < best_distance {
+            best = u;
+            best_distance = d;
+        }
+    }
+
+    // golden section on the bracket around the best sample
+    let ratio = 0.618_034;
+    let (mut lo, mut hi) = (best - step, best + step);
+    if !closed {
+        (lo, hi) = (lo.max(0.0), hi.min(1.0));
+    }
+    for _ in 0..24 {
+        let a = hi - ratio * (hi - lo);
+        let b = lo + ratio * (hi - lo);
+        if distance(a) < distance(b) {
+            hi = b;
+        } else {
+            lo = a;
+        }
+    }
+    let u = wrap(0.5 * (lo + hi));
+    if closed {
+        return u;
+    }
+    // the bracket midpoint never reaches an end, an open curve's end has to be tried on its own
+    [0.0, 1.0].into_iter().fold(u, |u, end| if distance(end) <= distance(u) { end } else { u })
+}
+
+/// Twice the signed area, positive for counter clockwise
+pub fn signed_area(points: &[Vec2]) -> f32 {
+    (0..points.len())
+        .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+        .sum()
+}
+
+impl CamProfile {
+    pub fn point(&self, u: f32) -> Vec2 {
+        let u = u.rem_euclid(1.0);
+        match self {
+            CamProfile::RadialLift { .. } | CamProfile::Polar { .. } => {
+                let angle = u * TAU;
+                Vec2::from_angle(angle) * self.radius(angle)
+            }
+            CamProfile::Spline { points } => {
+                let n = points.len();
+                if n == 0 {
+                    return Vec2::ZERO;
+                }
+                let position = u * n as f32;
+                let i = (position.floor() as usize).min(n - 1);
+                let at = |k: usize| points[(i + k + n - 1) % n];
+                catmull_rom(at(0), at(1), at(2), at(3), position - i as f32)
+            }
+        }
+    }
+
+    /// Polar radius, only meaningful for the radial profiles
+    pub fn radius(&self, angle: f32) -> f32 {
+        match self {
+            CamProfile::RadialLift { base_radius, lift } => {
+                let n = lift.len();
+                if n == 0 {
+                    return *base_radius;
+                }
+                let angle = angle.rem_euclid(TAU);
+                // segment i runs from lift[i] to lift[i + 1], the last one wraps through a full turn
+                let i = lift.iter().rposition(|(a, _)| *a <= angle).unwrap_or(n - 1);
+                let start = lift[i].0;
+                let mut span = lift[(i + 1) % n].0 - start;
+                if span <= 0.0 {
+                    span += TAU;
+                }
+                let t = ((angle - start).rem_euclid(TAU) / span).clamp(0.0, 1.0);
+                let at = |k: usize| lift[(i + k + n - 1) % n].1;
+                base_radius + catmull_rom(at(0), at(1), at(2), at(3), t)
+            }
+            CamProfile::Polar { base_radius, harmonics } => {
+                base_radius + harmonics.iter().enumerate()
+                    .map(|(k, (a, b))| {
+                        let k = (k + 1) as f32;
+                        a * (k * angle).cos() + b * (k * angle).sin()
+                    })
+                    .sum::<f32>()
+            }
+            CamProfile::Spline { .. } => self.point(angle / TAU).length(),
+        }
+    }
+
+    pub fn outline(&self, samples: usize) -> Vec<Vec2> {
+        (0..samples).map(|i| self.point(i as f32 / samples as f32)).collect()
+    }
+
+    /// Closest point on the outline and the outward unit normal there
+    pub fn closest(&self, target: Vec2) -> (Vec2, Vec2) {
+        let u = closest_parameter(|u| self.point(u).distance_squared(target), 128, true);
+        let point = self.point(u);
+        let h = 1e-3;
+        let tangent = self.point(u + h) - self.point(u - h);
+        // counter clockwise outline, outward is to the right of the tangent
+        let normal = Vec2::new(tangent.y, -tangent.x).normalize_or_zero();
+        (point, normal)
+    }
+}
+
+impl PathCurve {
+    /// Circular arc from `start` through `through` to `end`, None if the points are collinear
+    pub fn arc_through(start: Vec3, through: Vec3, end: Vec3) -> Option<PathCurve> {
+        let (a, b) = (start - through, end - through);
+        let normal = a.cross(b);
+        if normal.length_squared() < 1e-12 {
+            return None;
+        }
+        // circumcentre of the three points
+        let centre = through
+            + (b * a.length_squared() - a * b.length_squared()).cross(normal) / (2.0 * normal.length_squared());
+        let normal = normal.normalize();
+        let radius = start - centre;
+        let sweep_to = |p: Vec3| {
+            let r = p - centre;
+            normal.dot(radius.cross(r)).atan2(radius.dot(r)).rem_euclid(std::f32::consts::TAU)
+        };
+        // go the way that passes `through`
+        let (mid, mut sweep) = (sweep_to(through), sweep_to(end));
+        if mid > sweep {
+            sweep -= std::f32::consts::TAU;
+        }
+        Some(PathCurve::Arc { centre, radius, normal, sweep })
+    }
+
+    pub fn point(&self, u: f32) -> Vec3 {
+        let u = u.clamp(0.0, 1.0);
+        match self {
+            PathCurve::Arc { centre, radius, normal, sweep } => {
+                let angle = sweep * u;
+                *centre + *radius * angle.cos() + normal.cross(*radius) * angle.sin()
+            }
+            PathCurve::Polyline { points } => {
+                let Some((i, t)) = segment(points.len(), u) else {
+                    return points.first().copied().unwrap_or(Vec3::ZERO);
+                };
+                points[i].lerp(points[i + 1], t)
+            }
+            PathCurve::Bezier { points } => {
+                // de Casteljau
+                let mut points = points.clone();
+                for n in (1..points.len()).rev() {
+                    for i in 0..n {
+                        points[i] = points[i].lerp(points[i + 1], u);
+                    }
+                }
+                points.first().copied().unwrap_or(Vec3::ZERO)
+            }
+            PathCurve::CatmullRom { points } => {
+                let Some((i, t)) = segment(points.len(), u) else {
+                    return points.first().copied().unwrap_or(Vec3::ZERO);
+                };
+                // ends repeat the end point
+                let at = |k: isize| points[(i as isize + k).clamp(0, points.len() as isize - 1) as usize];
+                catmull_rom(at(-1), at(0), at(1), at(2), t)
+            }
+        }
+    }
+
+    /// Closest parameter and point to `target`
+    pub fn closest(&self, target: Vec3) -> (f32, Vec3) {
+        let u = closest_parameter(|u| self.point(u).distance_squared(target), 128, false);
+        (u, self.point(u))
+    }
+
+    /// Unit tangent at `u`, by central difference
+    pub fn tangent(&self, u: f32) -> Vec3 {
+        let h = 1e-3;
+        (self.point((u + h).min(1.0)) - self.point((u - h).max(0.0))).normalize_or_zero()
+    }
+
+    pub fn samples(&self, count: usize) -> Vec<Vec3> {
+        let count = count.max(2);
+        (0..count).map(|i| self.point(i as f32 / (count - 1) as f32)).collect()
+    }
+
+    /// Same curve with its points and directions mapped, for moving between frames
+    pub fn transformed(&self, point: impl Fn(Vec3) -> Vec3, direction: impl Fn(Vec3) -> Vec3) -> PathCurve {
+        match self {
+            PathCurve::Arc { centre, radius, normal, sweep } => PathCurve::Arc {
+                centre: point(*centre),
+                radius: direction(*radius),
+                normal: direction(*normal),
+                sweep: *sweep,
+            },
+            PathCurve::Polyline { points } => PathCurve::Polyline { points: points.iter().map(|p| point(*p)).collect() },
+            PathCurve::Bezier { points } => PathCurve::Bezier { points: points.iter().map(|p| point(*p)).collect() },
+            PathCurve::CatmullRom { points } => PathCurve::CatmullRom { points: points.iter().map(|p| point(*p)).collect() },
+        }
+    }
+}
+
+// Segment index and local t of u over n points spaced evenly in parameter
+fn segment(n: usize, u: f32) -> Option<(usize, f32)> {
+    if n < 2 {
+        return None;
+    }
+    let position = u * (n - 1) as f32;
+    let i = (position.floor() as usize).min(n - 2);
+    Some((i, position - i as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_off_the_ends_is_the_end() {
+        let curve = PathCurve::Polyline { points: vec![Vec3::ZERO, Vec3::X, Vec3::new(2.0, 1.0, 0.0)] };
+        assert_eq!(curve.closest(Vec3::new(-0.5, 0.2, 0.0)).0, 0.0);
+        assert_eq!(curve.closest(Vec3::new(2.5, 1.5, 0.0)).0, 1.0);
+        let (u, point) = curve.closest(Vec3::new(0.5, 0.3, 0.0));
+        assert!(u > 0.0 && u < 1.0 && point.distance(Vec3::new(0.5, 0.0, 0.0)) < 1e-3);
+    }
+}
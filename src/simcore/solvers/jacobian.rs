@@ -0,0 +1,90 @@
+use crate::simcore::types::*;
+use crate::simcore::solvers::linalg::*;
+use glam::Vec3;
+use std::collections::HashMap;
+
+/// Constraint Jacobian of the whole mechanism at the current pose.
+/// Columns are x, y, z of every joint in `joints` order, rows are every active constraint row.
+#[derive(Debug, Clone)]
+pub struct Jacobian {
+    pub joints: Vec<JointId>,
+    pub rows: Vec<ConstraintRow>,
+    pub owners: Vec<usize>, // index into Simulation::constraints for each row
+    pub matrix: Matrix,
+}
+
+impl Jacobian {
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.joints.len() * 3
+    }
+
+    /// Row residuals, C(x)
+    pub fn values(&self) -> Vec<f64> {
+        self.rows.iter().map(|row| row.value as f64).collect()
+    }
+
+    /// Inverse mass for each column
+    pub fn column_weights(&self, sim: &Simulation) -> Vec<f64> {
+        self.joints.iter()
+            .flat_map(|id| {
+                let w = sim.joints.get(*id).map_or(0.0, |j| j.inv_mass) as f64;
+                [w, w, w]
+            })
+            .collect()
+    }
+
+    /// Split a column vector back into one Vec3 per joint
+    pub fn per_joint(&self, column_vector: &[f64]) -> Vec<(JointId, Vec3)> {
+        self.joints.iter().enumerate()
+            .map(|(i, id)| {
+                let v = Vec3::new(
+                    column_vector[i * 3] as f32,
+                    column_vector[i * 3 + 1] as f32,
+                    column_vector[i * 3 + 2] as f32,
+                );
+                (*id, v)
+            })
+            .collect()
+    }
+}
+
+impl Simulation {
+    pub fn jacobian(&self) -> Jacobian {
+        let joints: Vec<JointId> = self.joints.iter().map(|(id, _)| id).collect();
+        let column_of: HashMap<JointId, usize> = joints.iter().enumerate().map(|(i, id)| (*id, i * 3)).collect();
+
+        let mut rows = Vec::new();
+        let mut owners = Vec::new();
+        for (index, constraint) in self.constraints.iter().enumerate() {
+            for row in constraint.rows(self) {
+                rows.push(row);
+                owners.push(index);
+            }
+        }
+
+        let mut matrix = zeros(rows.len(), joints.len() * 3);
+        for (r, row) in rows.iter().enumerate() {
+            for (id, grad) in &row.gradient {
+                if let Some(&c) = column_of.get(id) {
+                    matrix[r][c] += grad.x as f64;
+                    matrix[r][c + 1] += grad.y as f64;
+                    matrix[r][c + 2] += grad.z as f64;
+                }
+            }
+        }
+
+        Jacobian { joints, rows, owners, matrix }
+    }
+
+    /// Sum of squared row residuals
+    pub fn residual_norm_sq(&self) -> f64 {
+        self.constraints.iter()
+            .flat_map(|c| c.rows(self))
+            .map(|row| (row.value as f64).powi(2))
+            .sum()
+    }
+}
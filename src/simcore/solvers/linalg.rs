@@ -0,0 +1,188 @@
+// Small dense helpers, mechanisms here have tens of rows so nothing fancy is needed.
+// Everything is f64 so the solvers are not limited by the arithmetic.
+
+pub type Matrix = Vec<Vec<f64>>;
+
+pub fn zeros(rows: usize, cols: usize) -> Matrix {
+    vec![vec![0.0; cols]; rows]
+}
+
+/// Solve `a * x = b` for symmetric positive definite `a` with a Cholesky factorization.
+/// None if `a` is not positive definite.
+pub fn cholesky_solve(a: &Matrix, b: &[f64]) -> Option<Vec<f64>> {
+    let n = b.len();
+    let mut l = zeros(n, n);
+
+    for i in 0..n {
+        for j in 0..=i {
+            let sum = a[i][j] - dot(&l[i][..j], &l[j][..j]);
+            if i == j {
+                if sum <= 0.0 || !sum.is_finite() {
+                    return None;
+                }
+                l[i][i] = sum.sqrt();
+            } else {
+                l[i][j] = sum / l[j][j];
+            }
+        }
+    }
+
+    // forward, L y = b
+    let mut y = vec![0.0; n];
+    for i in 0..n {
+        let mut sum = b[i];
+        for k in 0..i {
+            sum -= l[i][k] * y[k];
+        }
+        y[i] = sum / l[i][i];
+    }
+
+    // back, L^T x = y
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let mut sum = y[i];
+        for k in i + 1..n {
+            sum -= l[k][i] * x[k];
+        }
+        x[i] = sum / l[i][i];
+    }
+
+    Some(x)
+}
+
+/// Orthonormal basis of the row space by modified Gram-Schmidt, rows taken in order.
+/// Also returns, per input row, whether it added a new direction (false = linearly dependent
+/// on the rows before it).
+pub fn row_basis(matrix: &Matrix, tolerance: f64) -> (Matrix, Vec<bool>) {
+    let mut basis: Matrix = Vec::new();
+    let mut independent = Vec::with_capacity(matrix.len());
+
+    for row in matrix {
+        let norm = dot(row, row).sqrt();
+        let mut v = row.clone();
+        for q in &basis {
+            let d = dot(&v, q);
+            for (vk, qk) in v.iter_mut().zip(q.iter()) {
+                *vk -= d * qk;
+            }
+        }
+        let remaining = dot(&v, &v).sqrt();
+
+        if norm > 0.0 && remaining > tolerance * norm.max(1.0) {
+            for vk in v.iter_mut() {
+                *vk /= remaining;
+            }
+            basis.push(v);
+            independent.push(true);
+        } else {
+            independent.push(false);
+        }
+    }
+
+    (basis, independent)
+}
+
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Solve (J J^T) y = b for the rows of `j`. Dependent rows make it singular, the diagonal is
+/// pushed up until it factors, so they share the result. Zeros if even that fails.
+pub fn row_space_solve(j: &Matrix, b: &[f64]) -> Vec<f64> {
+    let m = j.len();
+    let mut a = zeros(m, m);
+    for r in 0..m {
+        for c in r..m {
+            let sum = dot(&j[r], &j[c]);
+            a[r][c] = sum;
+            a[c][r] = sum;
+        }
+    }
+    let scale = (0..m).map(|i| a[i][i]).fold(1.0, f64::max);
+    let mut mu = 1e-10 * scale;
+    loop {
+        let mut damped = a.clone();
+        for (i, row) in damped.iter_mut().enumerate() {
+            row[i] += mu;
+        }
+        if let Some(y) = cholesky_solve(&damped, b) {
+            return y;
+        }
+        mu *= 100.0;
+        if mu > scale {
+            return vec![0.0; m];
+        }
+    }
+}
+
+/// Eigen decomposition of a symmetric matrix by cyclic Jacobi rotations.
+/// Returns eigenvalues in ascending order and the matching eigenvectors as rows.
+pub fn symmetric_eigen(matrix: &Matrix) -> (Vec<f64>, Matrix) {
+    let n = matrix.len();
+    let mut a = matrix.clone();
+    let mut v = zeros(n, n);
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+
+    for _sweep in 0..50 {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off < 1e-30 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (upper, lower) = a.split_at_mut(q);
+                for (apk, aqk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    let (x, y) = (*apk, *aqk);
+                    *apk = c * x - s * y;
+                    *aqk = s * x + c * y;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|i, j| a[*i][*i].total_cmp(&a[*j][*j]));
+    let values = order.iter().map(|i| a[*i][*i]).collect();
+    let vectors = order.iter().map(|i| (0..n).map(|k| v[k][*i]).collect()).collect();
+    (values, vectors)
+}
+
+/// a^T a
+pub fn gram(a: &Matrix, cols: usize) -> Matrix {
+    let mut g = zeros(cols, cols);
+    for row in a {
+        for i in 0..cols {
+            if row[i] == 0.0 {
+                continue;
+            }
+            for j in 0..cols {
+                g[i][j] += row[i] * row[j];
+            }
+        }
+    }
+    g
+}
@@ -1 +1,31 @@
-pub mod solvers;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod solvers;
+pub mod linalg;
+pub mod jacobian;
+pub mod newton;
+pub mod curves;
+
+use crate::simcore::types::*;
+pub use solvers::{PbdSolver, XpbdSolver};
+pub use newton::NewtonSolver;
+
+/// A position solver backend, moves joints until the constraints hold
+pub trait Solver: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+    fn solve(&mut self, sim: &mut Simulation, dt: f32, tolerance: f32, max_iterations: usize) -> SolveReport;
+}
+
+impl Default for Box<dyn Solver> {
+    fn default() -> Self {
+        Box::new(PbdSolver)
+    }
+}
+
+/// Every backend, for pickers in the UI
+pub fn solver_backends() -> Vec<Box<dyn Solver>> {
+    vec![
+        Box::new(PbdSolver),
+        Box::new(XpbdSolver),
+        Box::new(NewtonSolver::default()),
+    ]
+}
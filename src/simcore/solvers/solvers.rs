@@ -1,14 +1,186 @@
 use crate::simcore::types::*; 
-use glam::{Vec3, Vec2};
+use crate::simcore::solvers::Solver;
+use glam::{Mat3, Quat, Vec3, Vec2};
 use std::any::Any;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
-use crate::simcore::bindings::apply_distance;
 impl Simulation {
-    pub fn step(&mut self, dt: f32, iterations: usize) {
-        let iterations = iterations * 2;
-        for _ in 0..iterations {
-            self.solve_constraints();
+    /// Advance the simulation by `dt` seconds.
+    /// A `dt` of zero only projects the constraints (quasi-static, used while dragging),
+    /// otherwise this is a full position based dynamics step:
+    /// apply forces -> predict from velocity -> project constraints -> recover velocity from the displacement.
+    /// Projection stops early once every residual is below `tolerance`, or after `iterations` sweeps.
+    pub fn step(&mut self, dt: f32, iterations: usize, tolerance: f32) -> SolveReport {
+        self.reset_lambdas();
+
+        if dt <= 0.0 {
+            let report = self.solve(dt, tolerance, iterations);
+            self.advance_constraints();
+            return report;
+        }
+
+        // Forces from springs and other elements kick the velocities first
+        for (joint_id, force) in self.element_forces() {
+            if let Some(joint) = self.joints.get_mut(joint_id) {
+                joint.velocity += force * (joint.inv_mass * dt);
+            }
+        }
+
+        // Predict
+        let damping = (1.0 - self.damping * dt).clamp(0.0, 1.0);
+        let mut previous: Vec<(JointId, Vec3)> = Vec::with_capacity(self.joints.len());
+        for (joint_id, joint) in self.joints.iter_mut() {
+            let start = joint.position.as_vec3();
+            previous.push((joint_id, start));
+            if joint.inv_mass > 0.0 {
+                joint.velocity *= damping;
+                joint.position = Position::Vec3(start + joint.velocity * dt);
+            } else {
+                joint.velocity = Vec3::ZERO;
+            }
+        }
+
+        // Time dependent constraints (drivers) target the end of the step
+        self.time += dt;
+
+        // Project
+        let report = self.solve(dt, tolerance, iterations);
+
+        // Update velocities
+        for (joint_id, start) in previous {
+            if let Some(joint) = self.joints.get_mut(joint_id) {
+                joint.velocity = (joint.position.as_vec3() - start) / dt;
+            }
+        }
+
+        self.advance_constraints();
+        report
+    }
+
+    /// Let stateful constraints catch up with the solved pose
+    pub fn advance_constraints(&mut self) {
+        let mut constraints = std::mem::take(&mut self.constraints);
+        for constraint in constraints.iter_mut() {
+            constraint.advance(self);
+        }
+        self.constraints = constraints;
+    }
+
+    /// Run the selected solver backend on the current pose.
+    pub fn solve(&mut self, dt: f32, tolerance: f32, max_iterations: usize) -> SolveReport {
+        // Take the solver out temporarily to avoid borrow conflicts
+        let mut solver = std::mem::take(&mut self.solver);
+        let report = solver.solve(self, dt, tolerance, max_iterations);
+        self.solver = solver;
+        report
+    }
+
+    /// Repeat `sweep` until the largest residual is within `tolerance` or `max_iterations` is hit.
+    pub fn iterate(
+        &mut self,
+        dt: f32,
+        tolerance: f32,
+        max_iterations: usize,
+        mut sweep: impl FnMut(&mut Simulation),
+    ) -> SolveReport {
+        let mut iterations = 0;
+        let mut report = self.residual_report(dt, tolerance);
+
+        while !report.converged && iterations < max_iterations {
+            sweep(self);
+            iterations += 1;
+            report = self.residual_report(dt, tolerance);
+        }
+
+        report.iterations = iterations;
+        report.max_iterations = max_iterations;
+        report
+    }
+
+    /// Residual of every constraint at the current pose, without moving anything.
+    pub fn residual_report(&self, dt: f32, tolerance: f32) -> SolveReport {
+        let mut report = SolveReport::default();
+
+        for (index, constraint) in self.constraints.iter().enumerate() {
+            let residual = self.residual(constraint.as_ref(), dt);
+            report.max_residual = report.max_residual.max(residual);
+            if residual > tolerance {
+                report.violated.push((index, residual));
+            }
+        }
+
+        report.converged = report.violated.is_empty();
+        report
+    }
+
+    // A compliant constraint under XPBD is converged when C + alpha * lambda = 0,
+    // not when C itself is zero. Only XPBD fills the lambdas, other backends see plain C.
+    fn residual(&self, constraint: &dyn Constraint, dt: f32) -> f32 {
+        let state = constraint.xpbd();
+        if state.lambdas.is_empty() || state.compliance <= 0.0 || dt <= 0.0 {
+            return constraint.error(self);
+        }
+
+        let alpha = state.compliance / (dt * dt);
+        let rows = constraint.rows(self);
+        rows.iter().zip(state.matched(&rows))
+            .map(|(row, lambda)| (row.value + alpha * lambda).abs())
+            .fold(0.0, f32::max)
+    }
+
+    /// Zero every joint velocity, leaving positions untouched.
+    pub fn reset_velocities(&mut self) {
+        for (_, joint) in self.joints.iter_mut() {
+            joint.velocity = Vec3::ZERO;
+        }
+    }
+
+    pub fn reset_lambdas(&mut self) {
+        for constraint in self.constraints.iter_mut() {
+            let state = constraint.xpbd_mut();
+            state.lambdas.clear();
+            state.keys.clear();
+        }
+    }
+
+    // One XPBD sweep, lambdas keep accumulating until the next reset_lambdas
+    pub fn solve_constraints_xpbd(&mut self, dt: f32) {
+        let mut constraints = std::mem::take(&mut self.constraints);
+
+        for constraint in constraints.iter_mut() {
+            let rows = constraint.rows(self);
+            let state = constraint.xpbd_mut();
+            // rows that come and go (limits, contacts) take their own lambda along
+            state.lambdas = state.matched(&rows);
+            state.keys = rows.iter().map(|row| row.key).collect();
+            // with no time step there is nothing to be soft against, treat as rigid
+            let alpha = if dt > 0.0 { state.compliance / (dt * dt) } else { 0.0 };
+
+            for (row, lambda) in rows.iter().zip(state.lambdas.iter_mut()) {
+                let w: f32 = row.gradient.iter()
+                    .map(|(id, grad)| self.joints.get(*id).map_or(0.0, |j| j.inv_mass) * grad.length_squared())
+                    .sum();
+                if w + alpha < 1e-9 {
+                    continue;
+                }
+
+                let mut delta_lambda = (-row.value - alpha * *lambda) / (w + alpha);
+                // a stop only ever pushes, its multiplier can't go negative
+                if row.unilateral {
+                    delta_lambda = delta_lambda.max(-*lambda);
+                }
+                *lambda += delta_lambda;
+
+                for (id, grad) in &row.gradient {
+                    if let Some(joint) = self.joints.get_mut(*id) {
+                        let correction = *grad * (joint.inv_mass * delta_lambda);
+                        joint.position = Position::Vec3(joint.position.as_vec3() + correction);
+                    }
+                }
+            }
         }
+
+        self.constraints = constraints;
     }
 
     // Solve all constraints once
@@ -39,6 +211,34 @@ impl Simulation {
     }
 }
 
+/// Gauss-Seidel projection, every constraint snaps straight to its target
+#[derive(Debug, Clone, Default)]
+pub struct PbdSolver;
+
+impl Solver for PbdSolver {
+    fn name(&self) -> &'static str {
+        "PBD"
+    }
+
+    fn solve(&mut self, sim: &mut Simulation, dt: f32, tolerance: f32, max_iterations: usize) -> SolveReport {
+        sim.iterate(dt, tolerance, max_iterations, |sim| sim.solve_constraints())
+    }
+}
+
+/// Extended PBD, compliance and lambdas make stiffness independent of the iteration count
+#[derive(Debug, Clone, Default)]
+pub struct XpbdSolver;
+
+impl Solver for XpbdSolver {
+    fn name(&self) -> &'static str {
+        "XPBD"
+    }
+
+    fn solve(&mut self, sim: &mut Simulation, dt: f32, tolerance: f32, max_iterations: usize) -> SolveReport {
+        sim.iterate(dt, tolerance, max_iterations, |sim| sim.solve_constraints_xpbd(dt))
+    }
+}
+
 impl Constraint for FixedPositionConstraint {
     fn apply(&self, sim: &mut Simulation) {
         if let Some(joint) = sim.joints.get_mut(self.joint_id) {
@@ -55,6 +255,21 @@ impl Constraint for FixedPositionConstraint {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some(joint) = sim.joints.get(self.joint_id) else {
+            return Vec::new();
+        };
+        let error = joint.position.as_vec3() - self.target_position.as_vec3();
+        vec![
+            ConstraintRow::new(error.x, vec![(self.joint_id, Vec3::X)]),
+            ConstraintRow::new(error.y, vec![(self.joint_id, Vec3::Y)]),
+            ConstraintRow::new(error.z, vec![(self.joint_id, Vec3::Z)]),
+        ]
+    }
+
+    xpbd_field!();
+
 }
 
 impl Constraint for DistanceConstraint {
@@ -65,9 +280,12 @@ impl Constraint for DistanceConstraint {
             let error: f32 = current_distance - self.target_distance;
 
             if error.abs() > 1e-6 && current_distance > 0.0 {
-                let correction = delta.normalize().scale(error * 0.5);
-                joint_a.position = joint_a.position.add(correction);
-                joint_b.position = joint_b.position.sub(correction);
+                let Some((share_a, share_b)) = mass_shares(joint_a.inv_mass, joint_b.inv_mass) else {
+                    return;
+                };
+                let correction = delta.normalize().scale(error);
+                joint_a.position = joint_a.position.add(correction.scale(share_a));
+                joint_b.position = joint_b.position.sub(correction.scale(share_b));
             }
         }
     }
@@ -85,11 +303,87 @@ impl Constraint for DistanceConstraint {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let (Some(a), Some(b)) = (sim.joints.get(self.joint_a), sim.joints.get(self.joint_b)) else {
+            return Vec::new();
+        };
+        let delta = b.position.as_vec3() - a.position.as_vec3();
+        let current_distance = delta.length();
+        if current_distance <= 0.0 {
+            return Vec::new();
+        }
+        let dir = delta / current_distance;
+        vec![ConstraintRow::new(
+            current_distance - self.target_distance,
+            vec![(self.joint_a, -dir), (self.joint_b, dir)],
+        )]
+    }
+
+    xpbd_field!();
+
+}
+
+impl Constraint for DistanceRangeConstraint {
+    fn apply(&self, sim: &mut Simulation) {
+        if let Some((joint_a, joint_b)) = sim.get_two_joints_mut(self.joint_a, self.joint_b) {
+            let delta = joint_b.position.sub(joint_a.position);
+            let current_distance = delta.length();
+            // inside the range the pair is free, outside it goes to the nearest end
+            let error = current_distance - current_distance.clamp(self.min_distance, self.max_distance);
+
+            if error.abs() > 1e-6 && current_distance > 0.0 {
+                let Some((share_a, share_b)) = mass_shares(joint_a.inv_mass, joint_b.inv_mass) else {
+                    return;
+                };
+                let correction = delta.normalize().scale(error);
+                joint_a.position = joint_a.position.add(correction.scale(share_a));
+                joint_b.position = joint_b.position.sub(correction.scale(share_b));
+            }
+        }
+    }
+
+    fn is_satisfied(&self, sim: &Simulation) -> bool {
+        match (sim.joints.get(self.joint_a), sim.joints.get(self.joint_b)) {
+            (Some(a), Some(b)) => {
+                let dist = a.position.distance(b.position);
+                dist >= self.min_distance - 1e-6 && dist <= self.max_distance + 1e-6
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let (Some(a), Some(b)) = (sim.joints.get(self.joint_a), sim.joints.get(self.joint_b)) else {
+            return Vec::new();
+        };
+        let delta = b.position.as_vec3() - a.position.as_vec3();
+        let current_distance = delta.length();
+        if current_distance <= 0.0 {
+            return Vec::new();
+        }
+        let dir = delta / current_distance;
+        ConstraintRow::limit(
+            current_distance,
+            (self.min_distance, self.max_distance),
+            vec![(self.joint_a, -dir), (self.joint_b, dir)],
+        ).into_iter().collect()
+    }
+
+    xpbd_field!();
+
 }
 
 impl Constraint for PlaneConstraint {
     fn apply(&self, sim: &mut Simulation) {
         if let Some(joint) = sim.joints.get_mut(self.joint_id) {
+            if joint.inv_mass == 0.0 {
+                return;
+            }
             let position = joint.position.as_vec3();
             let normal = self.normal;
             let to_plane = position - self.plane_point;
@@ -113,15 +407,34 @@ impl Constraint for PlaneConstraint {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some(joint) = sim.joints.get(self.joint_id) else {
+            return Vec::new();
+        };
+        let normal = self.normal.normalize();
+        let distance_to_plane = (joint.position.as_vec3() - self.plane_point).dot(normal);
+        vec![ConstraintRow::new(distance_to_plane, vec![(self.joint_id, normal)])]
+    }
+
+    xpbd_field!();
+
 }
 
 impl Constraint for PrismaticConstraintVector {
     fn apply(&self, sim: &mut Simulation) {
         if let Some(joint) = sim.joints.get_mut(self.joint_id) {
+            // the line itself is fixed in the world, so a ground joint has nothing to give
+            if joint.inv_mass == 0.0 {
+                return;
+            }
             let axis_dir = self.axis.normalize();
             let joint_pos = joint.position.as_vec3();
             let to_joint = joint_pos - self.origin;
-            let proj_length = to_joint.dot(axis_dir);
+            let mut proj_length = to_joint.dot(axis_dir);
+            if let Some((min, max)) = self.limits {
+                proj_length = proj_length.clamp(min, max);
+            }
             let projected_pos = self.origin + axis_dir * proj_length;
             joint.position = Position::Vec3(projected_pos);
         }
@@ -135,7 +448,8 @@ impl Constraint for PrismaticConstraintVector {
             let proj_length = to_joint.dot(axis_dir);
             let projected_pos = self.origin + axis_dir * proj_length;
             let dist = (joint_pos - projected_pos).length();
-            dist < 1e-6
+            let within = self.limits.is_none_or(|(min, max)| proj_length >= min - 1e-6 && proj_length <= max + 1e-6);
+            dist < 1e-6 && within
         } else {
             false
         }
@@ -144,6 +458,28 @@ impl Constraint for PrismaticConstraintVector {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some(joint) = sim.joints.get(self.joint_id) else {
+            return Vec::new();
+        };
+        // two directions perpendicular to the axis, the joint must not move along either
+        let axis = self.axis.normalize();
+        let (u, v) = axis.any_orthonormal_pair();
+        let to_joint = joint.position.as_vec3() - self.origin;
+        let mut rows = vec![
+            ConstraintRow::new(to_joint.dot(u), vec![(self.joint_id, u)]),
+            ConstraintRow::new(to_joint.dot(v), vec![(self.joint_id, v)]),
+        ];
+        // and a travel limit only once it is hit
+        if let Some(limits) = self.limits {
+            rows.extend(ConstraintRow::limit(to_joint.dot(axis), limits, vec![(self.joint_id, axis)]));
+        }
+        rows
+    }
+
+    xpbd_field!();
+
 }
 
 impl Constraint for PrismaticConstraintLink {
@@ -159,6 +495,8 @@ impl Constraint for PrismaticConstraintLink {
             joint_id: self.joint_id,
             origin: self.origin,
             axis: axis_vec,
+            limits: None,
+            xpbd: XpbdState::default(),
         };
         prismatic_vec.apply(sim);
     }
@@ -174,6 +512,8 @@ impl Constraint for PrismaticConstraintLink {
             joint_id: self.joint_id,
             origin: self.origin,
             axis: axis_vec,
+            limits: None,
+            xpbd: XpbdState::default(),
         };
         prismatic_vec.is_satisfied(sim)
     }
@@ -181,6 +521,25 @@ impl Constraint for PrismaticConstraintLink {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        // axis is frozen at its current direction, only the sliding joint gets a gradient
+        let axis_vec = match self.get_link_axis(sim) {
+            Some(axis) if axis.length_squared() > 0.0 => axis,
+            _ => return Vec::new(),
+        };
+        let prismatic_vec = PrismaticConstraintVector {
+            joint_id: self.joint_id,
+            origin: self.origin,
+            axis: axis_vec,
+            limits: None,
+            xpbd: XpbdState::default(),
+        };
+        prismatic_vec.rows(sim)
+    }
+
+    xpbd_field!();
+
 }
 
 impl PrismaticConstraintLink {
@@ -194,139 +553,1529 @@ impl PrismaticConstraintLink {
         Some(joint_b.position.as_vec3() - joint_a.position.as_vec3())
     }
 }
-//broken as shit bro
-impl Constraint for FixedAngleConstraint { 
+impl FixedAngleConstraint {
+    /// Target angle of arm B from arm A at `time`, the drive replaces the fixed angle when set
+    pub fn target(&self, time: f32) -> f32 {
+        self.drive.as_ref().map_or(self.target_angle, |profile| profile.value(time))
+    }
+
+    /// Current signed angle from arm A to arm B about the axis, with both arms projected onto the plane
+    pub fn measure(&self, sim: &Simulation) -> Option<(f32, Vec3, Vec3)> {
+        let pivot = sim.joints.get(self.pivot_joint_id)?.position.as_vec3();
+        let axis = self.axis.normalize();
+        let planar = |id: JointId| -> Option<Vec3> {
+            let arm = sim.joints.get(id)?.position.as_vec3() - pivot;
+            let planar = arm - axis * axis.dot(arm);
+            (planar.length_squared() > 1e-12).then_some(planar)
+        };
+        let (arm_a, arm_b) = (planar(self.joint_a_id)?, planar(self.joint_b_id)?);
+        let angle = axis.dot(arm_a.cross(arm_b)).atan2(arm_a.dot(arm_b));
+        Some((angle, arm_a, arm_b))
+    }
+
+    // Wrapped to [-pi, pi] so a driven angle can sweep through the half turn
+    fn angle_error(&self, angle: f32, time: f32) -> f32 {
+        let error = angle - self.target(time);
+        wrap_angle(error)
+    }
+}
+
+impl Constraint for FixedAngleConstraint {
     fn apply(&self, sim: &mut Simulation) {
-        let pivot_pos = if let Some(pivot_joint) = sim.joints.get(self.pivot_joint_id) {
-            pivot_joint.position
-        } else {
+        let Some((angle, arm_a, arm_b)) = self.measure(sim) else {
             return;
         };
-    
-        if let Some((joint_a, joint_b)) = sim.get_two_joints_mut(self.joint_a_id, self.joint_b_id) {
-            let r_a = (joint_a.position.sub(pivot_pos)).length();
-            let r_b = (joint_b.position.sub(pivot_pos)).length();
-        
-            // Debug: Print current distances
-            println!("r_a: {}, r_b: {}", r_a, r_b);
-        
-            let target_distance = (r_a.powi(2) + r_b.powi(2) - 2.0 * r_a * r_b * self.target_angle.cos()).sqrt();
-            
-            let distance_constraint = DistanceConstraint{
-                joint_a: self.joint_a_id,
-                joint_b: self.joint_b_id,
-                target_distance:target_distance,
-            };
+        let error = self.angle_error(angle, sim.time);
+        if error.abs() < 1e-7 {
+            return;
+        }
 
-            distance_constraint.apply(sim);
+        // Turn both arms about the pivot, shared by how easily each one turns (inv_mass / r^2)
+        let inv_mass = |id: JointId| sim.joints.get(id).map_or(0.0, |j| j.inv_mass);
+        let turn_a = inv_mass(self.joint_a_id) / arm_a.length_squared();
+        let turn_b = inv_mass(self.joint_b_id) / arm_b.length_squared();
+        if turn_a + turn_b <= 0.0 {
+            // Both arms pinned, only the pivot can move
+            let rows = self.rows(sim);
+            project_rows(sim, &rows);
+            return;
+        }
+        let axis = self.axis.normalize();
+        let Some(pivot) = sim.joints.get(self.pivot_joint_id).map(|j| j.position.as_vec3()) else {
+            return;
+        };
+        for (id, turn) in [
+            (self.joint_a_id, error * turn_a / (turn_a + turn_b)),
+            (self.joint_b_id, -error * turn_b / (turn_a + turn_b)),
+        ] {
+            if let Some(joint) = sim.joints.get_mut(id) {
+                let arm = joint.position.as_vec3() - pivot;
+                joint.position = Position::Vec3(pivot + rotate_vec_in_plane(arm, axis, turn));
+            }
         }
     }
+
     fn is_satisfied(&self, sim: &Simulation) -> bool {
-        let pivot: Option<&Joint> = sim.joints.get(self.pivot_joint_id);
-        let joint_a = sim.joints.get(self.joint_a_id);
-        let joint_b = sim.joints.get(self.joint_b_id);
-    
-        if let (Some(pivot), Some(joint_a), Some(joint_b)) = (pivot, joint_a, joint_b) {
-            let pivot_pos = pivot.position.as_vec3();
-            let a_pos = joint_a.position.as_vec3();
-            let b_pos = joint_b.position.as_vec3();
-    
-            let r_a = (a_pos - pivot_pos).length();
-            let r_b = (b_pos - pivot_pos).length();
-            let target_dist = (r_a.powi(2) + r_b.powi(2) - 2.0 * r_a * r_b * self.target_angle.cos()).sqrt();
-    
-            let actual_dist = (b_pos - a_pos).length();
-            let epsilon = 1e-4;
-    
-            (actual_dist - target_dist).abs() < epsilon
-        } else {
-            false
-        }
+        self.measure(sim)
+            .map(|(angle, _, _)| self.angle_error(angle, sim.time).abs() < 1e-5)
+            .unwrap_or(false)
     }
 
     fn as_any(&self) -> &dyn Any {
         self
     }
 
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some((angle, arm_a, arm_b)) = self.measure(sim) else {
+            return Vec::new();
+        };
+        // angle = angle(B) - angle(A), each arm's gradient is axis x planar / |planar|^2
+        let axis = self.axis.normalize();
+        let gradient_a = -axis.cross(arm_a) / arm_a.length_squared();
+        let gradient_b = axis.cross(arm_b) / arm_b.length_squared();
+        vec![ConstraintRow::new(
+            self.angle_error(angle, sim.time),
+            vec![
+                (self.joint_a_id, gradient_a),
+                (self.joint_b_id, gradient_b),
+                (self.pivot_joint_id, -(gradient_a + gradient_b)),
+            ],
+        )]
+    }
+
+    xpbd_field!();
+}
+
+
+impl CarriedFrame {
+    /// Carried by the link from `origin` to `arm` as it is now
+    pub fn on_link(sim: &Simulation, origin: JointId, arm: JointId) -> Option<Self> {
+        let direction = joint_direction(sim, origin, arm)?;
+        Some(Self { link: Some((origin, arm)), direction })
+    }
+
+    /// Rotation since the last rebase
+    pub fn rotation(&self, sim: &Simulation) -> Quat {
+        self.link
+            .and_then(|(origin, arm)| joint_direction(sim, origin, arm))
+            .map_or(Quat::IDENTITY, |direction| Quat::from_rotation_arc(self.direction, direction))
+    }
+
+    /// Start measuring from the current pose, returns the rotation to fold into what the frame carries
+    pub fn rebase(&mut self, sim: &Simulation) -> Quat {
+        let rotation = self.rotation(sim);
+        if let Some(direction) = self.link.and_then(|(origin, arm)| joint_direction(sim, origin, arm)) {
+            self.direction = direction;
+        }
+        rotation
+    }
+}
+
+impl RevoluteConstraint {
+    /// Current (axis, zero direction), turned with the frame since the last rebase
+    pub fn axes(&self, sim: &Simulation) -> (Vec3, Vec3) {
+        let rotation = self.frame.rotation(sim);
+        (rotation * self.axis, rotation * self.zero_direction)
+    }
+
+    /// Current (signed angle, arm projected onto the hinge plane, axial distance from the pivot)
+    pub fn measure(&self, sim: &Simulation) -> Option<(f32, Vec3, f32)> {
+        let (axis, zero) = self.axes(sim);
+        let arm = sim.joints.get(self.moving_joint_id)?.position.as_vec3()
+            - sim.joints.get(self.pivot_joint_id)?.position.as_vec3();
+        let axial = axis.dot(arm);
+        let planar = arm - axis * axial;
+        if planar.length_squared() < 1e-12 {
+            return None;
+        }
+        let angle = axis.dot(zero.cross(planar)).atan2(zero.dot(planar));
+        Some((angle, planar, axial))
+    }
 }
 
+// Unit direction from one joint to another
+fn joint_direction(sim: &Simulation, from: JointId, to: JointId) -> Option<Vec3> {
+    let offset = sim.joints.get(to)?.position.as_vec3() - sim.joints.get(from)?.position.as_vec3();
+    let direction = offset.normalize_or_zero();
+    (direction != Vec3::ZERO).then_some(direction)
+}
 
 impl Constraint for RevoluteConstraint {
     fn apply(&self, sim: &mut Simulation) {
-        let (pivot, moving) = match sim.get_two_joints_mut(self.pivot_joint_id, self.moving_joint_id) {
-            Some((a, b)) => (a, b),
-            None => return,
+        let Some((angle, planar, axial)) = self.measure(sim) else {
+            return;
         };
+        let (axis, _) = self.axes(sim);
 
-        let pivot_pos = pivot.position.as_vec3();
-        let moving_pos = moving.position.as_vec3();
+        // Back into the hinge plane, then turn about the axis onto the nearest limit
+        let clamped = angle.clamp(self.min_angle, self.max_angle);
+        let turned = rotate_vec_in_plane(planar, axis, clamped - angle);
+        let correction = turned - planar + axis * (self.offset - axial);
+        if correction.length_squared() < 1e-14 {
+            return;
+        }
 
-        let current_vec = (moving_pos - pivot_pos).normalize();
-        let rest_vec = self.rest_direction.normalize();
+        let Some((pivot, moving)) = sim.get_two_joints_mut(self.pivot_joint_id, self.moving_joint_id) else {
+            return;
+        };
+        let Some((share_pivot, share_moving)) = mass_shares(pivot.inv_mass, moving.inv_mass) else {
+            return;
+        };
+        pivot.position = Position::Vec3(pivot.position.as_vec3() - correction * share_pivot);
+        moving.position = Position::Vec3(moving.position.as_vec3() + correction * share_moving);
+    }
 
-        // Rotation axis = plane normal
-        let plane_normal = rest_vec.cross(current_vec).normalize_or_zero();
-        if plane_normal.length_squared() == 0.0 {
-            return; // degenerate case: no clear rotation plane
-        }
+    fn is_satisfied(&self, sim: &Simulation) -> bool {
+        let Some((angle, _, axial)) = self.measure(sim) else {
+            return false;
+        };
+        (axial - self.offset).abs() < 1e-5
+            && angle >= self.min_angle - 1e-5
+            && angle <= self.max_angle + 1e-5
+    }
 
-        let dot = rest_vec.dot(current_vec).clamp(-1.0, 1.0);
-        let angle = dot.acos();
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some((angle, planar, axial)) = self.measure(sim) else {
+            return Vec::new();
+        };
+        let (axis, _) = self.axes(sim);
+        let mut rows = vec![ConstraintRow::new(
+            axial - self.offset,
+            vec![(self.pivot_joint_id, -axis), (self.moving_joint_id, axis)],
+        )];
+
+        // d(angle)/d(moving) = axis x planar / |planar|^2
+        let gradient = axis.cross(planar) / planar.length_squared();
+        rows.extend(ConstraintRow::limit(
+            angle,
+            (self.min_angle, self.max_angle),
+            vec![(self.pivot_joint_id, -gradient), (self.moving_joint_id, gradient)],
+        ));
+        rows
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        let rotation = self.frame.rebase(sim);
+        self.axis = rotation * self.axis;
+        self.zero_direction = rotation * self.zero_direction;
+    }
+}
+    
+   
+// a lambda stays with its pair of links, the top bit is left for the limit side
+fn contact_key(links: (LinkId, LinkId)) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    links.hash(&mut hasher);
+    hasher.finish() >> 1
+}
+
+impl Constraint for ContactConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 
-        // Signed angle: cross product gives direction
-        let cross = rest_vec.cross(current_vec);
-        let signed_angle = if cross.dot(plane_normal) < 0.0 { -angle } else { angle };
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        // the gap grows along the normal at the first link's closest point and against it at the second's
+        sim.clearances().into_iter()
+            .filter_map(|contact| {
+                let (ends_a, ends_b) = contact.points;
+                let gradient = ends_a.iter().map(|(id, weight)| (*id, contact.normal * *weight))
+                    .chain(ends_b.iter().map(|(id, weight)| (*id, -contact.normal * *weight)))
+                    .collect();
+                ConstraintRow::limit(contact.clearance, (0.0, f32::INFINITY), gradient)
+                    .map(|row| row.keyed(contact_key(contact.links)))
+            })
+            .collect()
+    }
 
-        // Check bounds
-        if signed_angle < self.min_angle || signed_angle > self.max_angle {
-            let clamped_angle = signed_angle.clamp(self.min_angle, self.max_angle);
+    xpbd_field!();
+}
 
-            // Rotate rest_vec to target angle in plane of plane_normal
-            let rotated_vec = rotate_vec_in_plane(rest_vec, plane_normal, clamped_angle);
+impl ConeConstraint {
+    /// Current (angle from the rest direction, arm, rest direction)
+    pub fn measure(&self, sim: &Simulation) -> Option<(f32, Vec3, Vec3)> {
+        let arm = sim.joints.get(self.moving_joint_id)?.position.as_vec3()
+            - sim.joints.get(self.pivot_joint_id)?.position.as_vec3();
+        if arm.length_squared() < 1e-12 {
+            return None;
+        }
+        let rest = self.frame.rotation(sim) * self.rest_direction;
+        Some((arm.cross(rest).length().atan2(arm.dot(rest)), arm, rest))
+    }
+}
 
-            // Maintain original length
-            let dist = (moving_pos - pivot_pos).length();
-            moving.position = Position::Vec3(pivot_pos + rotated_vec * dist);
+impl Constraint for ConeConstraint {
+    fn apply(&self, sim: &mut Simulation) {
+        let Some((angle, arm, rest)) = self.measure(sim) else {
+            return;
+        };
+        if angle <= self.max_angle {
+            return;
         }
-    }   
+        // Swing the arm back towards the rest direction onto the rim of the cone
+        let mut normal = arm.cross(rest).normalize_or_zero();
+        if normal == Vec3::ZERO {
+            normal = arm.any_orthonormal_vector();
+        }
+        let correction = rotate_vec_in_plane(arm, normal, angle - self.max_angle) - arm;
 
-    fn is_satisfied(&self, sim: &Simulation) -> bool {
-        
-        let pivot = sim.joints.get(self.pivot_joint_id).unwrap();
-        let moving = sim.joints.get(self.moving_joint_id).unwrap();
-        
-        let pivot_pos = pivot.position.as_vec3();
-        let moving_pos = moving.position.as_vec3();
+        let Some((pivot, moving)) = sim.get_two_joints_mut(self.pivot_joint_id, self.moving_joint_id) else {
+            return;
+        };
+        let Some((share_pivot, share_moving)) = mass_shares(pivot.inv_mass, moving.inv_mass) else {
+            return;
+        };
+        pivot.position = Position::Vec3(pivot.position.as_vec3() - correction * share_pivot);
+        moving.position = Position::Vec3(moving.position.as_vec3() + correction * share_moving);
+    }
 
-        let current_vec = (moving_pos - pivot_pos).normalize();
-        let rest_vec = self.rest_direction.normalize();
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 
-        // Rotation axis = plane normal
-        let plane_normal = rest_vec.cross(current_vec).normalize_or_zero();
-        if plane_normal.length_squared() == 0.0 {
-            return false; // degenerate case: no clear rotation plane
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some((angle, arm, rest)) = self.measure(sim) else {
+            return Vec::new();
+        };
+        if angle.sin() < 1e-6 {
+            return Vec::new();
         }
+        // d(angle)/d(arm) = -(rest - u (u . rest)) / (|arm| sin(angle))
+        let length = arm.length();
+        let unit = arm / length;
+        let gradient = -(rest - unit * unit.dot(rest)) / (length * angle.sin());
+        ConstraintRow::limit(
+            angle,
+            (0.0, self.max_angle),
+            vec![(self.pivot_joint_id, -gradient), (self.moving_joint_id, gradient)],
+        ).into_iter().collect()
+    }
 
-        let dot = rest_vec.dot(current_vec).clamp(-1.0, 1.0);
-        let angle = dot.acos();
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        self.rest_direction = self.frame.rebase(sim) * self.rest_direction;
+    }
+}
 
-        // Signed angle: cross product gives direction
-        let cross = rest_vec.cross(current_vec);
-        let signed_angle = if cross.dot(plane_normal) < 0.0 { -angle } else { angle };
-        signed_angle >= self.min_angle - 1e-5 && signed_angle <= self.max_angle + 1e-5
+impl UniversalConstraint {
+    /// Cardan angles (alpha about axis A, beta towards axis A) of an arm against the axes.
+    /// With C = A x B the arm is R_A(alpha) R_B(beta) C, so beta = asin(A . u)
+    pub fn angles(axis_a: Vec3, axis_b: Vec3, arm: Vec3) -> (f32, f32) {
+        let unit = arm.normalize_or_zero();
+        let cross = axis_a.cross(axis_b);
+        let alpha = (-axis_b.dot(arm)).atan2(cross.dot(arm));
+        (alpha, axis_a.dot(unit).clamp(-1.0, 1.0).asin())
+    }
 
-        
+    /// Current (axis A, axis B), turned with the frame since the last rebase
+    pub fn axes(&self, sim: &Simulation) -> (Vec3, Vec3) {
+        let rotation = self.frame.rotation(sim);
+        (rotation * self.axis_a, rotation * self.axis_b)
     }
+}
+
+impl Constraint for UniversalConstraint {
     fn as_any(&self) -> &dyn Any {
         self
-    } 
     }
-    
-   
-/// Rotate a vector by a given angle in the plane defined by a normal.
-/// Uses Rodrigues' rotation formula (but no quats).
-fn rotate_vec_in_plane(vec: Vec3, normal: Vec3, angle: f32) -> Vec3 {
-    let cos = angle.cos();
-    let sin = angle.sin();
-    vec * cos + normal.cross(vec) * sin + normal * normal.dot(vec) * (1.0 - cos)
-}
\ No newline at end of file
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let (Some(pivot), Some(moving)) = (sim.joints.get(self.pivot_joint_id), sim.joints.get(self.moving_joint_id)) else {
+            return Vec::new();
+        };
+        let arm = moving.position.as_vec3() - pivot.position.as_vec3();
+        let (axis_a, axis_b) = self.axes(sim);
+        let (alpha, beta) = Self::angles(axis_a, axis_b, arm);
+        let mut rows = Vec::new();
+        let mut limit_row = |key: u64, offset: f32, gradient: Vec3| {
+            rows.extend(ConstraintRow::limit(
+                offset,
+                (-self.limit, self.limit),
+                vec![(self.pivot_joint_id, -gradient), (self.moving_joint_id, gradient)],
+            ).map(|row| row.keyed(key)));
+        };
+
+        // alpha is the angle of the arm about A, d(alpha)/d(arm) = A x planar / |planar|^2
+        let planar = arm - axis_a * axis_a.dot(arm);
+        if planar.length_squared() > 1e-12 {
+            limit_row(0, wrap_angle(alpha - self.rest.0), axis_a.cross(planar) / planar.length_squared());
+        }
+        // d(beta)/d(arm) = (A - u (u . A)) / (|arm| cos(beta))
+        let length = arm.length();
+        if length > 1e-6 && beta.cos() > 1e-6 {
+            let unit = arm / length;
+            limit_row(1, beta - self.rest.1, (axis_a - unit * unit.dot(axis_a)) / (length * beta.cos()));
+        }
+        rows
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        let rotation = self.frame.rebase(sim);
+        self.axis_a = rotation * self.axis_a;
+        self.axis_b = rotation * self.axis_b;
+    }
+}
+
+impl CrankDriverConstraint {
+    /// Current (angle, arm projected onto the rotation plane, axial part of the arm)
+    fn measure(&self, sim: &Simulation) -> Option<(f32, Vec3, Vec3)> {
+        let pivot = sim.joints.get(self.pivot_joint_id)?;
+        let moving = sim.joints.get(self.moving_joint_id)?;
+        let axis = self.axis.normalize();
+        let arm = moving.position.as_vec3() - pivot.position.as_vec3();
+        let axial = axis * axis.dot(arm);
+        let planar = arm - axial;
+        if planar.length_squared() == 0.0 {
+            return None;
+        }
+        let perp = axis.cross(self.zero_direction);
+        let angle = planar.dot(perp).atan2(planar.dot(self.zero_direction));
+        Some((angle, planar, axial))
+    }
+
+    pub fn target_angle(&self, time: f32) -> f32 {
+        self.profile.value(time)
+    }
+
+    // Angle error wrapped to [-pi, pi] so the crank can run through full revolutions
+    fn angle_error(&self, angle: f32, time: f32) -> f32 {
+        let error = angle - self.target_angle(time);
+        wrap_angle(error)
+    }
+}
+
+impl Constraint for CrankDriverConstraint {
+    fn apply(&self, sim: &mut Simulation) {
+        let Some((angle, planar, axial)) = self.measure(sim) else {
+            return;
+        };
+        let error = self.angle_error(angle, sim.time);
+        if error.abs() < 1e-7 {
+            return;
+        }
+
+        let axis = self.axis.normalize();
+        let target = angle - error;
+        let direction = self.zero_direction * target.cos() + axis.cross(self.zero_direction) * target.sin();
+        let correction = direction * planar.length() + axial - (planar + axial);
+
+        let Some((pivot, moving)) = sim.get_two_joints_mut(self.pivot_joint_id, self.moving_joint_id) else {
+            return;
+        };
+        let Some((share_pivot, share_moving)) = mass_shares(pivot.inv_mass, moving.inv_mass) else {
+            return;
+        };
+        pivot.position = Position::Vec3(pivot.position.as_vec3() - correction * share_pivot);
+        moving.position = Position::Vec3(moving.position.as_vec3() + correction * share_moving);
+    }
+
+    fn is_satisfied(&self, sim: &Simulation) -> bool {
+        self.measure(sim)
+            .map(|(angle, _, _)| self.angle_error(angle, sim.time).abs() < 1e-5)
+            .unwrap_or(false)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some((angle, planar, _)) = self.measure(sim) else {
+            return Vec::new();
+        };
+        // d(angle)/d(moving) = axis x planar / |planar|^2
+        let gradient = self.axis.normalize().cross(planar) / planar.length_squared();
+        vec![ConstraintRow::new(
+            self.angle_error(angle, sim.time),
+            vec![(self.pivot_joint_id, -gradient), (self.moving_joint_id, gradient)],
+        )]
+    }
+
+    xpbd_field!();
+}
+
+impl ShapeMatchConstraint {
+    // Heavier joints pull the fit more, fixed joints pull it all the way
+    fn weights(&self, sim: &Simulation) -> Vec<f32> {
+        self.joints.iter()
+            .map(|id| match sim.joints.get(*id).map(|j| j.inv_mass) {
+                Some(inv_mass) if inv_mass > 0.0 => 1.0 / inv_mass,
+                _ => 1e6,
+            })
+            .collect()
+    }
+
+    /// Where each joint should be, the rest shape moved and turned onto the current pose
+    pub fn goals(&self, sim: &Simulation) -> Option<(Vec<Vec3>, Quat)> {
+        let positions: Vec<Vec3> = self.joints.iter()
+            .map(|id| sim.joints.get(*id).map(|j| j.position.as_vec3()))
+            .collect::<Option<_>>()?;
+        let weights = self.weights(sim);
+        let total: f32 = weights.iter().sum();
+        let centre = positions.iter().zip(&weights).map(|(p, w)| *p * *w).sum::<Vec3>() / total;
+        let rest_centre = self.rest.iter().zip(&weights).map(|(r, w)| *r * *w).sum::<Vec3>() / total;
+
+        // A = sum w (p - c)(r - c0)^T, its rotational part is the best fit
+        let mut moment = Mat3::ZERO;
+        for ((p, r), w) in positions.iter().zip(&self.rest).zip(&weights) {
+            let (p, r) = ((*p - centre) * *w, *r - rest_centre);
+            moment += Mat3::from_cols(p * r.x, p * r.y, p * r.z);
+        }
+        let rotation = extract_rotation(moment, self.rotation);
+
+        let goals = self.rest.iter().map(|r| centre + rotation * (*r - rest_centre)).collect();
+        Some((goals, rotation))
+    }
+}
+
+impl Constraint for ShapeMatchConstraint {
+    fn apply(&self, sim: &mut Simulation) {
+        let Some((goals, _)) = self.goals(sim) else {
+            return;
+        };
+        for (id, goal) in self.joints.iter().zip(goals) {
+            if let Some(joint) = sim.joints.get_mut(*id).filter(|joint| joint.inv_mass > 0.0) {
+                joint.position = Position::Vec3(goal);
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let mut rows = Vec::new();
+        for (i, j) in rigid_pairs(&self.rest) {
+            let (Some(a), Some(b)) = (sim.joints.get(self.joints[i]), sim.joints.get(self.joints[j])) else {
+                continue;
+            };
+            let delta = b.position.as_vec3() - a.position.as_vec3();
+            let direction = delta.normalize_or_zero();
+            rows.push(ConstraintRow::new(
+                delta.length() - self.rest[i].distance(self.rest[j]),
+                vec![(self.joints[i], -direction), (self.joints[j], direction)],
+            ));
+        }
+        rows
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        if let Some((_, rotation)) = self.goals(sim) {
+            self.rotation = rotation;
+        }
+    }
+}
+
+impl GearConstraint {
+    /// Pitch radii of (A, B) that mesh at the current centre distance, None for coaxial gears
+    pub fn pitch_radii(&self, sim: &Simulation) -> Option<(f32, f32)> {
+        let pivot_a = sim.joints.get(self.pivot_a)?.position.as_vec3();
+        let pivot_b = sim.joints.get(self.pivot_b)?.position.as_vec3();
+        let offset = pivot_b - pivot_a;
+        let centre_distance = (offset - self.axis * self.axis.dot(offset)).length();
+        // external: r_a + r_b = d, internal: |r_b - r_a| = d, and r_a / r_b = |ratio| either way
+        let denominator = (1.0 - self.ratio).abs();
+        if centre_distance < 1e-6 || denominator < 1e-6 {
+            return None;
+        }
+        let radius_b = centre_distance / denominator;
+        Some((radius_b * self.ratio.abs(), radius_b))
+    }
+
+    // Arm projected onto the gear plane
+    fn planar_arm(&self, sim: &Simulation, pivot: JointId, arm: JointId) -> Option<Vec3> {
+        let arm = sim.joints.get(arm)?.position.as_vec3() - sim.joints.get(pivot)?.position.as_vec3();
+        let planar = arm - self.axis * self.axis.dot(arm);
+        (planar.length_squared() > 1e-12).then_some(planar)
+    }
+
+    // Signed rotation from reference to planar about the axis
+    fn rotation(&self, reference: Vec3, planar: Vec3) -> f32 {
+        self.axis.dot(reference.cross(planar)).atan2(reference.dot(planar))
+    }
+}
+
+impl Constraint for GearConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let (Some(planar_a), Some(planar_b)) = (
+            self.planar_arm(sim, self.pivot_a, self.arm_a),
+            self.planar_arm(sim, self.pivot_b, self.arm_b),
+        ) else {
+            return Vec::new();
+        };
+        let angle_a = self.rotation(self.reference_a, planar_a);
+        let angle_b = self.rotation(self.reference_b, planar_b);
+
+        // d(angle)/d(arm) = axis x planar / |planar|^2, the pivot gets the opposite
+        let gradient_a = self.axis.cross(planar_a) / planar_a.length_squared() * -self.ratio;
+        let gradient_b = self.axis.cross(planar_b) / planar_b.length_squared();
+        vec![ConstraintRow::new(
+            angle_b - self.ratio * angle_a + self.phase,
+            vec![
+                (self.pivot_a, -gradient_a),
+                (self.arm_a, gradient_a),
+                (self.pivot_b, -gradient_b),
+                (self.arm_b, gradient_b),
+            ],
+        )]
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        let (Some(planar_a), Some(planar_b)) = (
+            self.planar_arm(sim, self.pivot_a, self.arm_a),
+            self.planar_arm(sim, self.pivot_b, self.arm_b),
+        ) else {
+            return;
+        };
+        // keep whatever error is left so it is not forgotten when the references move
+        self.phase = self.rows(sim).first().map_or(self.phase, |row| row.value);
+        self.reference_a = planar_a.normalize();
+        self.reference_b = planar_b.normalize();
+    }
+}
+
+impl RackPinionConstraint {
+    fn coupling(&self) -> RotationToTravel {
+        RotationToTravel {
+            joints: (self.pivot, self.arm, self.rack),
+            axis: self.axis,
+            direction: self.direction,
+            travel_per_radian: self.pitch_radius,
+        }
+    }
+}
+
+impl Constraint for RackPinionConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        self.coupling().row(sim, self.reference_arm, self.reference_offset, self.phase).into_iter().collect()
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        if let Some((arm, offset)) = self.coupling().measure(sim) {
+            self.phase = self.rows(sim).first().map_or(self.phase, |row| row.value);
+            self.reference_arm = arm.normalize();
+            self.reference_offset = offset;
+        }
+    }
+}
+
+impl ScrewConstraint {
+    fn coupling(&self) -> RotationToTravel {
+        RotationToTravel {
+            joints: (self.pivot, self.arm, self.nut),
+            axis: self.axis,
+            direction: self.axis,
+            travel_per_radian: self.lead / std::f32::consts::TAU,
+        }
+    }
+}
+
+impl Constraint for ScrewConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        self.coupling().row(sim, self.reference_arm, self.reference_offset, self.phase).into_iter().collect()
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        if let Some((arm, offset)) = self.coupling().measure(sim) {
+            self.phase = self.rows(sim).first().map_or(self.phase, |row| row.value);
+            self.reference_arm = arm.normalize();
+            self.reference_offset = offset;
+        }
+    }
+}
+
+/// Arm rotation about a pivot coupled to a joint sliding along a direction,
+/// shared by the rack and pinion and the screw
+struct RotationToTravel {
+    joints: (JointId, JointId, JointId), // pivot, arm, slider
+    axis: Vec3,
+    direction: Vec3,
+    travel_per_radian: f32,
+}
+
+impl RotationToTravel {
+    /// Planar arm and slider offset along the direction, both relative to the pivot
+    fn measure(&self, sim: &Simulation) -> Option<(Vec3, f32)> {
+        let (pivot, arm, slider) = self.joints;
+        let pivot = sim.joints.get(pivot)?.position.as_vec3();
+        let arm = sim.joints.get(arm)?.position.as_vec3() - pivot;
+        let planar = arm - self.axis * self.axis.dot(arm);
+        if planar.length_squared() < 1e-12 {
+            return None;
+        }
+        let offset = self.direction.dot(sim.joints.get(slider)?.position.as_vec3() - pivot);
+        Some((planar, offset))
+    }
+
+    // C = travel - travel_per_radian * rotation + phase, both since the references
+    fn row(&self, sim: &Simulation, reference_arm: Vec3, reference_offset: f32, phase: f32) -> Option<ConstraintRow> {
+        let (planar, offset) = self.measure(sim)?;
+        let rotation = self.axis.dot(reference_arm.cross(planar)).atan2(reference_arm.dot(planar));
+        let angle_gradient = self.axis.cross(planar) / planar.length_squared() * self.travel_per_radian;
+        let (pivot, arm, slider) = self.joints;
+        Some(ConstraintRow::new(
+            offset - reference_offset - self.travel_per_radian * rotation + phase,
+            vec![
+                (slider, self.direction),
+                (pivot, -self.direction + angle_gradient),
+                (arm, -angle_gradient),
+            ],
+        ))
+    }
+}
+
+impl BeltConstraint {
+    /// Pulley radius signed by wrap direction, positive counter clockwise about the axis
+    pub fn wrap_radius(&self, index: usize) -> f32 {
+        let radius = self.pulleys[index].radius;
+        if self.crossed && index % 2 == 1 { -radius } else { radius }
+    }
+
+    // Planar arm of a pulley and its rotation since the reference
+    fn rotation(&self, sim: &Simulation, pulley: &Pulley) -> Option<(Vec3, f32)> {
+        let pivot = sim.joints.get(pulley.pivot)?.position.as_vec3();
+        let arm = sim.joints.get(pulley.arm)?.position.as_vec3() - pivot;
+        let planar = arm - self.axis * self.axis.dot(arm);
+        if planar.length_squared() < 1e-12 {
+            return None;
+        }
+        let angle = self.axis.dot(pulley.reference_arm.cross(planar)).atan2(pulley.reference_arm.dot(planar));
+        Some((planar, angle))
+    }
+
+    /// Straight belt spans between tangent points, from each pulley to the next and back to the first
+    pub fn spans(&self, sim: &Simulation) -> Vec<(Vec3, Vec3)> {
+        let n = self.pulleys.len();
+        let centres: Option<Vec<Vec3>> = self.pulleys.iter()
+            .map(|p| sim.joints.get(p.pivot).map(|j| j.position.as_vec3()))
+            .collect();
+        let Some(centres) = centres else {
+            return Vec::new();
+        };
+
+        let mut spans = Vec::with_capacity(n);
+        for i in 0..n {
+            let j = (i + 1) % n;
+            let (ri, rj) = (self.wrap_radius(i), self.wrap_radius(j));
+            let offset = centres[j] - centres[i];
+            let offset = offset - self.axis * self.axis.dot(offset);
+            let distance = offset.length();
+            let sine = (rj - ri) / distance;
+            if distance < 1e-6 || sine.abs() > 1.0 {
+                // overlapping pulleys, there is no tangent
+                continue;
+            }
+            // n . (c - p) = signed radius on both pulleys
+            let u = offset / distance;
+            let normal = u * sine + self.axis.cross(u) * (1.0 - sine * sine).sqrt();
+            spans.push((centres[i] - normal * ri, centres[j] - normal * rj));
+        }
+        spans
+    }
+
+    /// Belt length, the straight spans plus the arcs wrapped on each pulley
+    pub fn length(&self, sim: &Simulation) -> f32 {
+        let spans = self.spans(sim);
+        let n = self.pulleys.len();
+        if spans.len() != n {
+            return 0.0;
+        }
+        let straight: f32 = spans.iter().map(|(a, b)| a.distance(*b)).sum();
+
+        let mut wrapped = 0.0;
+        for i in 0..n {
+            let Some(centre) = sim.joints.get(self.pulleys[i].pivot).map(|j| j.position.as_vec3()) else {
+                return 0.0;
+            };
+            let arrive = spans[(i + n - 1) % n].1 - centre;
+            let leave = spans[i].0 - centre;
+            let radius = self.wrap_radius(i);
+            // turn from arrive to leave in the wrap direction
+            let turn = self.axis.dot(arrive.cross(leave)).atan2(arrive.dot(leave)) * radius.signum();
+            wrapped += radius.abs() * turn.rem_euclid(std::f32::consts::TAU);
+        }
+        straight + wrapped
+    }
+}
+
+impl Constraint for BeltConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let measured: Option<Vec<(Vec3, f32)>> = self.pulleys.iter().map(|p| self.rotation(sim, p)).collect();
+        let Some(measured) = measured else {
+            return Vec::new();
+        };
+
+        // belt travel = signed radius * rotation, the same on every pulley
+        let mut rows = Vec::with_capacity(self.pulleys.len().saturating_sub(1));
+        for i in 0..self.pulleys.len().saturating_sub(1) {
+            let mut value = self.phases.get(i).copied().unwrap_or(0.0);
+            let mut gradient = Vec::with_capacity(4);
+            for (k, sign) in [(i, -1.0), (i + 1, 1.0)] {
+                let (planar, angle) = measured[k];
+                let coefficient = sign * self.wrap_radius(k);
+                let angle_gradient = self.axis.cross(planar) / planar.length_squared() * coefficient;
+                value += coefficient * angle;
+                gradient.push((self.pulleys[k].arm, angle_gradient));
+                gradient.push((self.pulleys[k].pivot, -angle_gradient));
+            }
+            rows.push(ConstraintRow::new(value, gradient));
+        }
+        rows
+    }
+
+    xpbd_field!();
+
+    fn advance(&mut self, sim: &Simulation) {
+        let measured: Option<Vec<(Vec3, f32)>> = self.pulleys.iter().map(|p| self.rotation(sim, p)).collect();
+        let Some(measured) = measured else {
+            return;
+        };
+        self.phases = self.rows(sim).iter().map(|row| row.value).collect();
+        for (pulley, (planar, _)) in self.pulleys.iter_mut().zip(measured) {
+            pulley.reference_arm = planar.normalize();
+        }
+    }
+}
+
+impl PointOnCurveConstraint {
+    /// Link frame (origin, local x, local y), local z is the axis. None for world curves.
+    pub fn frame(&self, sim: &Simulation) -> Option<(Vec3, Vec3, Vec3)> {
+        let (origin, arm) = self.link?;
+        planar_frame(sim, origin, arm, self.axis)
+    }
+
+    /// The curve in world space at the current pose
+    pub fn world_curve(&self, sim: &Simulation) -> Option<PathCurve> {
+        if self.link.is_none() {
+            return Some(self.curve.clone());
+        }
+        let (origin, x, y) = self.frame(sim)?;
+        let axis = self.axis;
+        Some(self.curve.transformed(
+            |p| origin + x * p.x + y * p.y + axis * p.z,
+            |v| x * v.x + y * v.y + axis * v.z,
+        ))
+    }
+}
+
+impl Constraint for PointOnCurveConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        match self.world_curve(sim) {
+            Some(curve) => curve_rows(sim, self.joint_id, &curve, self.link, self.axis),
+            None => Vec::new(),
+        }
+    }
+
+    xpbd_field!();
+}
+
+impl PinInSlotConstraint {
+    /// The slot centre line in world space at the current pose
+    pub fn world_slot(&self, sim: &Simulation) -> Option<PathCurve> {
+        let (origin, x, y) = planar_frame(sim, self.origin, self.arm, self.axis)?;
+        let axis = self.axis;
+        Some(self.slot.transformed(
+            |p| origin + x * p.x + y * p.y + axis * p.z,
+            |v| x * v.x + y * v.y + axis * v.z,
+        ))
+    }
+}
+
+impl Constraint for PinInSlotConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        match self.world_slot(sim) {
+            Some(slot) => curve_rows(sim, self.pin, &slot, Some((self.origin, self.arm)), self.axis),
+            None => Vec::new(),
+        }
+    }
+
+    xpbd_field!();
+}
+
+impl CamConstraint {
+    /// Cam frame in world space (origin, local x, local y)
+    pub fn frame(&self, sim: &Simulation) -> Option<(Vec3, Vec3, Vec3)> {
+        planar_frame(sim, self.pivot, self.arm, self.axis)
+    }
+
+    /// Profile outline in world space, for drawing
+    pub fn world_outline(&self, sim: &Simulation, samples: usize) -> Vec<Vec3> {
+        let Some((origin, x, y)) = self.frame(sim) else {
+            return Vec::new();
+        };
+        self.profile.outline(samples).into_iter().map(|p| origin + x * p.x + y * p.y).collect()
+    }
+}
+
+impl Constraint for CamConstraint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let (Some((origin, x, y)), Some(follower)) = (self.frame(sim), sim.joints.get(self.follower)) else {
+            return Vec::new();
+        };
+        let Some(arm) = sim.joints.get(self.arm) else {
+            return Vec::new();
+        };
+        let offset = follower.position.as_vec3() - origin;
+        let (point, normal) = self.profile.closest(Vec2::new(offset.dot(x), offset.dot(y)));
+        let contact = x * point.x + y * point.y; // relative to the pivot
+        let normal = x * normal.x + y * normal.y;
+
+        // C = n . (follower - contact) - roller radius
+        // the contact point moves with the pivot and turns with the cam angle,
+        // d(angle)/d(arm) = axis x planar / |planar|^2 as for the drivers
+        let planar = arm.position.as_vec3() - origin;
+        let planar = planar - self.axis * self.axis.dot(planar);
+        let angle_gradient = self.axis.cross(planar) / planar.length_squared();
+        let swing = normal.dot(self.axis.cross(contact));
+
+        vec![ConstraintRow::new(
+            normal.dot(offset - contact) - self.roller_radius,
+            vec![
+                (self.follower, normal),
+                (self.pivot, -normal + angle_gradient * swing),
+                (self.arm, -angle_gradient * swing),
+            ],
+        )]
+    }
+
+    xpbd_field!();
+}
+
+impl LinearActuatorConstraint {
+    pub fn target(&self, time: f32) -> Vec3 {
+        self.origin + self.axis * self.profile.value(time)
+    }
+}
+
+impl Constraint for LinearActuatorConstraint {
+    fn apply(&self, sim: &mut Simulation) {
+        let target = self.target(sim.time);
+        if let Some(joint) = sim.joints.get_mut(self.joint_id) {
+            if joint.inv_mass == 0.0 {
+                return;
+            }
+            joint.position = Position::Vec3(target);
+        }
+    }
+
+    fn is_satisfied(&self, sim: &Simulation) -> bool {
+        sim.joints.get(self.joint_id)
+            .map(|joint| joint.position.as_vec3().abs_diff_eq(self.target(sim.time), 1e-6))
+            .unwrap_or(false)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow> {
+        let Some(joint) = sim.joints.get(self.joint_id) else {
+            return Vec::new();
+        };
+        // first row is the drive along the axis, the other two are the guide
+        let error = joint.position.as_vec3() - self.target(sim.time);
+        let (u, v) = self.axis.any_orthonormal_pair();
+        vec![
+            ConstraintRow::new(error.dot(self.axis), vec![(self.joint_id, self.axis)]),
+            ConstraintRow::new(error.dot(u), vec![(self.joint_id, u)]),
+            ConstraintRow::new(error.dot(v), vec![(self.joint_id, v)]),
+        ]
+    }
+
+    xpbd_field!();
+}
+
+/// How a correction between two joints is split, by inverse mass.
+/// None when both joints are immovable.
+fn mass_shares(inv_mass_a: f32, inv_mass_b: f32) -> Option<(f32, f32)> {
+    let total = inv_mass_a + inv_mass_b;
+    if total <= 0.0 {
+        return None;
+    }
+    Some((inv_mass_a / total, inv_mass_b / total))
+}
+
+/// Rotate a vector by a given angle in the plane defined by a normal.
+/// Uses Rodrigues' rotation formula (but no quats).
+fn rotate_vec_in_plane(vec: Vec3, normal: Vec3, angle: f32) -> Vec3 {
+    let cos = angle.cos();
+    let sin = angle.sin();
+    vec * cos + normal.cross(vec) * sin + normal * normal.dot(vec) * (1.0 - cos)
+}
+
+/// Rigid projection of a set of rows, each row moves its joints along the gradient
+/// weighted by inverse mass (one Gauss-Seidel pass, like the XPBD sweep with zero compliance)
+pub(crate) fn project_rows(sim: &mut Simulation, rows: &[ConstraintRow]) {
+    for row in rows {
+        let w: f32 = row.gradient.iter()
+            .map(|(id, grad)| sim.joints.get(*id).map_or(0.0, |j| j.inv_mass) * grad.length_squared())
+            .sum();
+        if w < 1e-9 {
+            continue;
+        }
+        let step = -row.value / w;
+        for (id, grad) in &row.gradient {
+            if let Some(joint) = sim.joints.get_mut(*id) {
+                let correction = *grad * (joint.inv_mass * step);
+                joint.position = Position::Vec3(joint.position.as_vec3() + correction);
+            }
+        }
+    }
+}
+
+/// `angle` brought into [-pi, pi), the short way round
+pub fn wrap_angle(angle: f32) -> f32 {
+    (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI
+}
+
+/// Frame of a link turning about `axis`: origin at `origin`, local x towards `arm` in the plane
+/// normal to the axis, local y = axis x local x. None if the arm lies on the axis.
+pub fn planar_frame(sim: &Simulation, origin: JointId, arm: JointId, axis: Vec3) -> Option<(Vec3, Vec3, Vec3)> {
+    let origin = sim.joints.get(origin)?.position.as_vec3();
+    let arm = sim.joints.get(arm)?.position.as_vec3() - origin;
+    let x = (arm - axis * axis.dot(arm)).normalize_or_zero();
+    if x == Vec3::ZERO {
+        return None;
+    }
+    Some((origin, x, axis.cross(x)))
+}
+
+/// Pairs of points whose distances hold them all rigid, 3n - 6 of them where every pair would be
+/// redundant from five points on. Each point is tied to the nearest points before it that pin it:
+/// one off the line through it and the first, then one off the plane through all three. A flat
+/// body has no such third point and takes any off the line, it can't be rigid to first order anyway.
+pub fn rigid_pairs(points: &[Vec3]) -> Vec<(usize, usize)> {
+    let off_line = |a: Vec3, b: Vec3, c: Vec3| (b - a).cross(c - a).length_squared() > 1e-12;
+    let mut pairs = Vec::new();
+    for k in 1..points.len() {
+        let p = points[k];
+        let mut earlier: Vec<usize> = (0..k).collect();
+        earlier.sort_by(|a, b| points[*a].distance_squared(p).total_cmp(&points[*b].distance_squared(p)));
+
+        let mut anchors = vec![earlier[0]];
+        let second = earlier[1..].iter().find(|i| off_line(p, points[earlier[0]], points[**i]));
+        anchors.extend(second.or(earlier.get(1)));
+        if let [a, b] = anchors[..] {
+            let (a, b) = (points[a], points[b]);
+            let rest: Vec<usize> = earlier.iter().copied().filter(|i| !anchors.contains(i)).collect();
+            let third = rest.iter().find(|i| (a - p).cross(b - p).dot(points[**i] - p).abs() > 1e-6)
+                .or_else(|| rest.iter().find(|i| off_line(a, b, points[**i])));
+            anchors.extend(third);
+        }
+        pairs.extend(anchors.into_iter().map(|i| (i, k)));
+    }
+    pairs
+}
+
+/// Rows holding `joint_id` on a world space curve, across the curve inside it and at the end point
+/// beyond either end. A curve carried by a link (origin, arm) also pushes back on the link.
+fn curve_rows(
+    sim: &Simulation,
+    joint_id: JointId,
+    curve: &PathCurve,
+    link: Option<(JointId, JointId)>,
+    axis: Vec3,
+) -> Vec<ConstraintRow> {
+    let Some(joint) = sim.joints.get(joint_id) else {
+        return Vec::new();
+    };
+    let position = joint.position.as_vec3();
+    let (u, point) = curve.closest(position);
+    let error = position - point;
+
+    // past the nearer end along its tangent the tangent row acts as the stop
+    let tangent = curve.tangent(u);
+    let (n1, n2) = tangent.any_orthonormal_pair();
+    let end = if u < 0.5 { 0.0 } else { 1.0 };
+    let beyond = (position - curve.point(end)).dot(curve.tangent(end)) * (end * 2.0 - 1.0);
+    let directions = if beyond > 0.0 { vec![n1, n2, tangent] } else { vec![n1, n2] };
+
+    // on a link the curve point moves with the origin and turns about the axis
+    let link_motion = link.and_then(|(origin_id, arm_id)| {
+        let (origin, x, _) = planar_frame(sim, origin_id, arm_id, axis)?;
+        let planar_length = x.dot(sim.joints[arm_id].position.as_vec3() - origin);
+        Some((origin_id, arm_id, axis.cross(point - origin), axis.cross(x) / planar_length))
+    });
+
+    directions.into_iter()
+        .map(|direction| {
+            let mut gradient = vec![(joint_id, direction)];
+            if let Some((origin_id, arm_id, swing, angle_gradient)) = link_motion {
+                let swing = direction.dot(swing);
+                gradient.push((origin_id, -direction + angle_gradient * swing));
+                gradient.push((arm_id, -angle_gradient * swing));
+            }
+            ConstraintRow::new(error.dot(direction), gradient)
+        })
+        .enumerate()
+        // the stop is keyed by the end it is at
+        .map(|(k, row)| if k == 2 { row.keyed(end as u64) } else { row })
+        .collect()
+}
+
+/// Rotational part of `a` (polar decomposition) by iterating from `initial`,
+/// after Mueller et al. "A Robust Method to Extract the Rotational Part of Deformations"
+fn extract_rotation(a: Mat3, initial: Quat) -> Quat {
+    let mut q = initial.normalize();
+    for _ in 0..20 {
+        let r = Mat3::from_quat(q);
+        let omega = (r.x_axis.cross(a.x_axis) + r.y_axis.cross(a.y_axis) + r.z_axis.cross(a.z_axis))
+            / ((r.x_axis.dot(a.x_axis) + r.y_axis.dot(a.y_axis) + r.z_axis.dot(a.z_axis)).abs() + 1e-9);
+        let angle = omega.length();
+        if angle < 1e-9 {
+            break;
+        }
+        q = (Quat::from_axis_angle(omega / angle, angle) * q).normalize();
+    }
+    q
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+    use crate::simcore::solvers::solver_backends;
+
+    fn build(src: &str) -> Simulation {
+        DslCompiler::compile_to_simulation(UgokuParser::parse_dsl(src).unwrap()).unwrap()
+    }
+
+    fn joint(sim: &Simulation, name: &str) -> JointId {
+        sim.joints.iter().find(|(_, joint)| joint.name == name).unwrap().0
+    }
+
+    fn position(sim: &Simulation, name: &str) -> Vec3 {
+        sim.joints[joint(sim, name)].position.as_vec3()
+    }
+
+    fn run(sim: &mut Simulation, seconds: f32) {
+        for _ in 0..(seconds * 60.0).round() as usize {
+            sim.step(1.0 / 60.0, 40, 1e-6);
+        }
+    }
+
+    #[test]
+    fn step_carries_joints_along_their_velocity() {
+        let mut sim = build("sim free { joint p(0,0,0) }");
+        let p = joint(&sim, "p");
+        sim.joints[p].velocity = Vec3::new(1.0, 2.0, 0.0);
+        sim.step(0.5, 10, 1e-6);
+        assert!((sim.time - 0.5).abs() < 1e-6);
+        assert!(position(&sim, "p").distance(Vec3::new(0.5, 1.0, 0.0)) < 1e-6);
+
+        // a pendulum bob keeps its length and only moves across the string
+        let mut sim = build("sim pendulum { joint a(0,0,0) joint b(1,0,0) distance(a,b,1) fixed(a) }");
+        let b = joint(&sim, "b");
+        sim.joints[b].velocity = Vec3::new(0.0, 2.0, 0.0);
+        run(&mut sim, 0.5);
+        let (bob, velocity) = (position(&sim, "b"), sim.joints[b].velocity);
+        assert!((bob.length() - 1.0).abs() < 1e-4);
+        assert!(velocity.dot(bob).abs() < 0.1 * velocity.length(), "{bob:?} {velocity:?}");
+        assert!(bob.y > 0.5);
+    }
+
+    #[test]
+    fn compliance_leaves_the_constraint_stretched() {
+        let stretched = |src: &str| {
+            let mut sim = build(src);
+            sim.solver = Box::new(XpbdSolver);
+            let report = sim.step(1.0 / 60.0, 40, 1e-6);
+            (position(&sim, "a").distance(position(&sim, "b")), report.converged)
+        };
+        let (rigid, converged) = stretched("sim s { joint a(0,0,0) joint b(2,0,0) distance(a,b,1) fixed(a) }");
+        assert!(converged && (rigid - 1.0).abs() < 1e-4, "{rigid}");
+
+        // converged counts C + alpha lambda, so the soft solve is done while still stretched
+        let (soft, converged) = stretched("sim s { joint a(0,0,0) joint b(2,0,0) distance(a,b,1) compliance(0.001) fixed(a) }");
+        assert!(converged, "{soft}");
+        assert!(soft > 1.05 && soft < 2.0, "{soft}");
+    }
+
+    #[test]
+    fn heavier_joints_move_less() {
+        let mut sim = build("sim s { joint a(0,0,0) joint b(3,0,0) distance(a,b,1) mass(b, 3) }");
+        sim.step(0.0, 10, 1e-6);
+        // a carries 1, b 3, so a closes three quarters of the gap
+        assert!(position(&sim, "a").distance(Vec3::new(1.5, 0.0, 0.0)) < 1e-4, "{:?}", position(&sim, "a"));
+        assert!(position(&sim, "b").distance(Vec3::new(2.5, 0.0, 0.0)) < 1e-4, "{:?}", position(&sim, "b"));
+
+        let mut sim = build("sim s { joint a(0,0,0) joint b(3,0,0) distance(a,b,1) fixed(a) }");
+        sim.step(0.0, 10, 1e-6);
+        assert_eq!(position(&sim, "a"), Vec3::ZERO);
+        assert!(position(&sim, "b").distance(Vec3::new(1.0, 0.0, 0.0)) < 1e-4);
+    }
+
+    #[test]
+    fn drive_turns_the_crank_at_its_speed() {
+        for solver in solver_backends() {
+            let mut sim = build("sim crank { joint a(0,0,0) joint b(1,0,0) distance(a,b,1) fixed(a) plane((b), Z) drive(a,b,90deg) }");
+            sim.solver = solver;
+            run(&mut sim, 1.0);
+            let crank = position(&sim, "b");
+            assert!(crank.distance(Vec3::Y) < 1e-2, "{} {crank:?}", sim.solver.name());
+        }
+    }
+
+    #[test]
+    fn actuator_follows_its_move_profile() {
+        let trapezoid = MotionProfile::Trapezoidal { distance: 2.0, max_speed: 1.0, acceleration: 2.0 };
+        // half a second up to speed, cruise, half a second down
+        for (time, travel) in [(0.0, 0.0), (0.5, 0.25), (1.25, 1.0), (2.5, 2.0), (4.0, 2.0)] {
+            assert!((trapezoid.value(time) - travel).abs() < 1e-5, "{time} {}", trapezoid.value(time));
+        }
+        let triangle = MotionProfile::Trapezoidal { distance: 1.0, max_speed: 10.0, acceleration: 1.0 };
+        assert!((triangle.value(1.0) - 0.5).abs() < 1e-5 && (triangle.value(2.0) - 1.0).abs() < 1e-5);
+        let scurve = MotionProfile::SCurve { distance: -2.0, max_speed: 1.0 };
+        assert!((scurve.value(2.0) + 1.0).abs() < 1e-5 && (scurve.value(4.0) + 2.0).abs() < 1e-5);
+
+        for solver in solver_backends() {
+            let mut sim = build("sim push { joint p(0,0,0) actuator(p, X, trapezoid(2,1,2)) }");
+            sim.solver = solver;
+            run(&mut sim, 1.25);
+            assert!(position(&sim, "p").distance(Vec3::X) < 1e-3, "{} {:?}", sim.solver.name(), position(&sim, "p"));
+            run(&mut sim, 1.75);
+            assert!(position(&sim, "p").distance(2.0 * Vec3::X) < 1e-3, "{} {:?}", sim.solver.name(), position(&sim, "p"));
+        }
+    }
+
+    #[test]
+    fn gear_turns_the_wheel_by_the_ratio() {
+        for solver in solver_backends() {
+            let mut sim = build(include_str!("../../examples/gearpair.ugoku"));
+            sim.solver = solver;
+            run(&mut sim, 1.0);
+            // pinion at 90deg/s, ratio -0.5
+            let wheel = position(&sim, "d") - position(&sim, "c");
+            let expected = 2.0 * Vec3::new(1.0, -1.0, 0.0).normalize();
+            assert!(wheel.distance(expected) < 2e-2, "{} {wheel:?}", sim.solver.name());
+        }
+    }
+
+    #[test]
+    fn cam_lifts_the_follower_by_its_profile() {
+        let src = "sim cam {
+    joint a(0,0,0)
+    joint b(1,0,0)
+    joint f(0,1,0)
+    distance(a,b,1)
+    fixed(a)
+    plane((b), Z)
+    prismatic_vector((f), Y, (0,0,0))
+    drive(a,b,90deg)
+    cam(a,b,f, polar(1, [(0.3,0)]))
+}";
+        for solver in solver_backends() {
+            let mut sim = build(src);
+            sim.solver = solver;
+            // r = 1 + 0.3 cos, the follower sits over the cam's 90deg until a quarter turn brings 0deg under it
+            run(&mut sim, 1.0);
+            let follower = position(&sim, "f");
+            assert!(follower.distance(Vec3::new(0.0, 1.3, 0.0)) < 1e-2, "{} {follower:?}", sim.solver.name());
+        }
+    }
+
+    #[test]
+    fn rack_and_screw_follow_the_pinion() {
+        let src = "sim rack {
+    joint a(0,0,0)
+    joint b(1,0,0)
+    joint r(0,2,0)
+    joint n(0,0,1)
+    distance(a,b,1)
+    fixed(a)
+    plane((b), Z)
+    prismatic_vector((r), X, (0,2,0))
+    prismatic_vector((n), Z, (0,0,0))
+    drive(a,b,90deg)
+    rack(a,b,r,0.5,X)
+    screw(a,b,n,2)
+}";
+        for solver in solver_backends() {
+            let mut sim = build(src);
+            sim.solver = solver;
+            // a quarter turn, the rack runs a quarter of the pitch circle and the nut a quarter of the lead
+            run(&mut sim, 1.0);
+            let (rack, nut) = (position(&sim, "r"), position(&sim, "n"));
+            assert!(rack.distance(Vec3::new(std::f32::consts::FRAC_PI_4, 2.0, 0.0)) < 1e-2, "{} {rack:?}", sim.solver.name());
+            assert!(nut.distance(Vec3::new(0.0, 0.0, 1.5)) < 1e-2, "{} {nut:?}", sim.solver.name());
+        }
+    }
+
+    #[test]
+    fn belt_turns_the_pulleys_by_their_radii() {
+        let open = include_str!("../../examples/beltdrive.ugoku");
+        for (src, turn) in [(open.to_string(), 1.0), (open.replace("open", "crossed"), -1.0)] {
+            for solver in solver_backends() {
+                let mut sim = build(&src);
+                sim.solver = solver;
+                let belt = |sim: &Simulation| sim.constraints.iter()
+                    .find_map(|c| c.as_any().downcast_ref::<BeltConstraint>().map(|belt| belt.length(sim)))
+                    .unwrap();
+                let length = belt(&sim);
+                // 45deg on the unit pulley is 90deg on the half size one, crossed belts reverse it
+                run(&mut sim, 1.0);
+                let driven = position(&sim, "d") - position(&sim, "c");
+                assert!(driven.distance(Vec3::new(0.0, 0.5 * turn, 0.0)) < 1e-2, "{} {driven:?}", sim.solver.name());
+                assert!((belt(&sim) - length).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn revolute_keeps_to_its_plane_and_limits() {
+        for solver in solver_backends() {
+            let mut sim = build("sim hinge { joint p(0,0,0) joint m(1,0,0) distance(p,m,1) fixed(p) revolute(p,m,Z,-45deg,45deg) }");
+            sim.solver = solver;
+            let m = joint(&sim, "m");
+            sim.joints[m].velocity = Vec3::new(0.0, 3.0, 1.0);
+            run(&mut sim, 1.0);
+            let arm = position(&sim, "m");
+            let name = sim.solver.name();
+            assert!(arm.z.abs() < 1e-3, "{name} {arm:?}");
+            assert!(arm.y.atan2(arm.x) < 45f32.to_radians() + 1e-2, "{name} {arm:?}");
+            assert!(arm.y.atan2(arm.x) > 0.5, "{name} {arm:?}");
+        }
+    }
+
+    const FOURBAR: &str = "sim fourbar {
+    joint a(0,0,0)
+    joint b(0,1,0)
+    joint c(3,2,0)
+    joint d(3,0,0)
+    link crank(a,b)
+    link coupler(b,c)
+    link rocker(c,d)
+    distance(a,b,1)
+    distance(b,c,3.1623)
+    distance(c,d,2)
+    fixed(a,d)
+    plane((a,b,c,d), Z)
+}";
+
+    fn dragged_fourbar() -> Simulation {
+        let mut sim = build(FOURBAR);
+        let b = sim.joints.iter().nth(1).unwrap().0;
+        sim.joints[b].position = Position::Vec3(Vec3::new(0.5, 0.9, 0.0));
+        sim
+    }
+
+    #[test]
+    fn newton_closes_a_fourbar_to_f32_precision() {
+        let mut sim = dragged_fourbar();
+        sim.solver = Box::new(crate::simcore::solvers::NewtonSolver::default());
+        // residuals of a model a few units across bottom out at a couple of f32 ulps
+        let report = sim.step(0.0, 5, 4.0 * f32::EPSILON);
+        assert!(report.converged, "{report:?}");
+        assert!(report.iterations <= 4);
+    }
+
+    #[test]
+    fn solve_stops_at_tolerance_and_reports_the_cap() {
+        let mut sim = dragged_fourbar();
+        let report = sim.step(0.0, 200, 1e-3);
+        assert!(report.converged);
+        assert!(report.iterations < 200);
+        assert!(report.max_residual <= 1e-3);
+        assert_eq!(report.max_iterations, 200);
+
+        let mut sim = dragged_fourbar();
+        let report = sim.step(0.0, 2, 1e-9);
+        assert!(!report.converged);
+        assert_eq!((report.iterations, report.max_iterations), (2, 2));
+        assert!(!report.violated.is_empty());
+    }
+
+    #[test]
+    fn body_distances_are_not_redundant() {
+        let sim = build("sim plate {
+    joint a(0,0,0)
+    joint b(2,0,0)
+    joint c(2,1,0)
+    joint d(1,2,0.3)
+    joint e(0,1,0.5)
+    joint f(1,1,1)
+    body plate(a, b, c, d, e, f)
+}");
+        assert_eq!(sim.constraints.len(), 3 * 6 - 6);
+        let mobility = sim.mobility();
+        assert!(mobility.redundant_constraints.is_empty());
+        assert_eq!(mobility.numerical, 6);
+        assert!(UgokuParser::parse_dsl("sim s { joint a(0,0,0) joint b(1,0,0) body p(a, b) }").is_err());
+    }
+
+    #[test]
+    fn lambdas_follow_their_rows() {
+        let state = XpbdState { compliance: 1e-3, lambdas: vec![1.0, 5.0], keys: vec![None, Some(2)] };
+        let row = |key: Option<u64>| ConstraintRow { key, ..ConstraintRow::new(0.0, Vec::new()) };
+        // another limit took the place of the one with a lambda, it starts from zero
+        assert_eq!(state.matched(&[row(None), row(Some(4))]), vec![1.0, 0.0]);
+        assert_eq!(state.matched(&[row(Some(4)), row(None), row(Some(2))]), vec![0.0, 1.0, 5.0]);
+    }
+
+    #[test]
+    fn rope_only_pulls() {
+        let src = "sim rope {
+    joint a(0,0,0)
+    joint b(1,0,0)
+    distance(a,b,..2)
+    fixed(a)
+}";
+        for solver in solver_backends() {
+            let mut sim = build(src);
+            sim.solver = solver;
+            let b = sim.joints.iter().nth(1).unwrap().0;
+            sim.joints[b].velocity = Vec3::new(-0.5, 0.0, 0.0);
+            sim.step(1.0 / 60.0, 20, 1e-6);
+            // slack, nothing holds it at its declared length
+            assert!(sim.constraints[0].rows(&sim).is_empty());
+            sim.joints[b].velocity = Vec3::new(3.0, 1.0, 0.0);
+            for _ in 0..60 {
+                sim.step(1.0 / 60.0, 20, 1e-6);
+            }
+            let length = sim.joints[b].position.as_vec3().length();
+            assert!(length < 2.0 + 1e-3, "{} {length}", sim.solver.name());
+        }
+        assert!(UgokuParser::parse_dsl("sim s { joint a(0,0,0) joint b(1,0,0) distance(a,b,..) }").is_err());
+    }
+
+    #[test]
+    fn contact_keys_keep_every_bit_of_the_pair() {
+        let link = |index: usize| LinkId::from_raw_parts(index, 0);
+        let key = contact_key((link(1), link(2)));
+        assert_ne!(key, contact_key((link(1 + (1 << 31)), link(2))));
+        assert_ne!(key, contact_key((link(2), link(1))));
+        // room for the side bit
+        assert_eq!(ConstraintRow::new(0.0, Vec::new()).keyed(key).key.unwrap() >> 1, key);
+    }
+
+    #[test]
+    fn curve_stops_at_both_ends() {
+        let src = "sim guide {
+    joint p(1,0,0)
+    on_curve(p, polyline([(0,0,0), (2,0,0)]))
+}";
+        for (target, stop) in [(Vec3::new(-0.5, 0.2, 0.0), Vec3::ZERO), (Vec3::new(2.5, -0.2, 0.0), Vec3::new(2.0, 0.0, 0.0))] {
+            let mut sim = build(src);
+            let id = sim.joints.iter().next().unwrap().0;
+            sim.joints[id].position = Position::Vec3(target);
+            assert_eq!(sim.constraints[0].rows(&sim).len(), 3);
+            sim.solve_constraints();
+            assert!(sim.joints[id].position.as_vec3().distance(stop) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn fixed_angle_turns_the_short_way() {
+        assert!((wrap_angle(1.5 * std::f32::consts::PI) + 0.5 * std::f32::consts::PI).abs() < 1e-6);
+        assert!((wrap_angle(-0.25) + 0.25).abs() < 1e-6);
+
+        // 90 degrees to -170 is 100 degrees further on, not 260 back
+        let mut sim = build("sim bend {
+    joint a(1,0,0)
+    joint p(0,0,0)
+    joint b(0,1,0)
+    distance(p,b,1)
+    fixed(a,p)
+    fixed_angle(a, p, b, -170deg, Z)
+}");
+        sim.solver = Box::new(crate::simcore::solvers::NewtonSolver::default());
+        assert!(sim.step(0.0, 20, 1e-6).converged);
+        let b = sim.joints.iter().nth(2).unwrap().1.position.as_vec3();
+        assert!((b.y.atan2(b.x).to_degrees() + 170.0).abs() < 1e-2, "{b:?}");
+    }
+
+    #[test]
+    fn joint_types_hold_their_limits() {
+        // a joint without a type only keeps its links together
+        let sim = build("sim bare { joint a(0,0,0) joint b(1,0,0) link l(a,b) distance(a,b,1) fixed(a) }");
+        assert_eq!(sim.constraints.len(), 2);
+
+        let src = "sim ball {
+    joint a(0,0,0) : ball(30deg)
+    joint b(1,0,0)
+    link arm(a,b)
+    distance(a,b,1)
+    fixed(a)
+}";
+        for solver in solver_backends() {
+            let mut sim = build(src);
+            sim.solver = solver;
+            let b = sim.joints.iter().nth(1).unwrap().0;
+            sim.joints[b].velocity = Vec3::new(0.0, 3.0, 2.0);
+            for _ in 0..60 {
+                sim.step(1.0 / 60.0, 20, 1e-6);
+            }
+            let angle = sim.joints[b].position.as_vec3().angle_between(Vec3::X);
+            assert!(angle < 30f32.to_radians() + 1e-3, "{} {}", sim.solver.name(), angle.to_degrees());
+        }
+    }
+
+    #[test]
+    fn pin_stops_at_slot_start() {
+        let src = "sim stop {
+    joint a(0,0,0)
+    joint b(2,0,0)
+    joint p(1,0,0)
+    link rail(a,b)
+    fixed(a,b)
+    slot(p, rail, (0.5, 0), (1.5, 0))
+}";
+        for solver in solver_backends() {
+            let mut sim = build(src);
+            sim.solver = solver;
+            let pin = sim.joints.iter().nth(2).unwrap().0;
+            sim.joints[pin].velocity = Vec3::new(-1.0, 0.3, 0.0);
+            for _ in 0..120 {
+                sim.step(1.0 / 60.0, 20, 1e-6);
+            }
+            let position = sim.joints[pin].position.as_vec3();
+            assert!(position.distance(Vec3::new(0.5, 0.0, 0.0)) < 1e-3, "{} {position:?}", sim.solver.name());
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use glam::Vec3;
+use crate::simcore::types::*;
+use crate::simcore::solvers::linalg::*;
+
+/// Force the constraints put on one joint to hold it still
+#[derive(Debug, Clone)]
+pub struct JointReaction {
+    pub joint_id: JointId,
+    /// Support reaction at a ground joint (inv_mass == 0), what the ground has to put in against
+    /// the loads and the rest of the mechanism. Zero at joints that can move, rails and planes
+    /// holding those only show up in their bearing force
+    pub support: Vec3,
+    /// Largest force any single constraint puts through the joint, what its bearing has to carry
+    pub bearing: f32,
+}
+
+/// Loads that hold a pose in equilibrium against the force elements
+#[derive(Debug, Clone, Default)]
+pub struct StaticReport {
+    pub reactions: Vec<JointReaction>,
+    /// Tension in every two joint link held by a distance constraint, negative in compression.
+    /// Bodies and links held any other way are left out
+    pub link_loads: Vec<(LinkId, f32)>,
+    /// Torque each driver has to supply, a force for linear actuators, by index into Simulation::constraints
+    pub driver_loads: Vec<(usize, f32)>,
+    /// Largest force the constraints can't balance, nonzero when the loads move an undriven DOF
+    pub unbalanced: f32,
+    /// Equations of constraints reaching a joint that can move, implied by the others. Their share
+    /// of the load is the least norm split, which a stiffer or looser real part would change.
+    /// Overlap between constraints on ground joints alone (a fixed joint in a plane) doesn't count
+    pub redundant_rows: usize,
+}
+
+impl Simulation {
+    /// Constraint forces balancing the force elements at the current pose, from the multipliers
+    /// of J^T lambda = -F. Redundant constraints share their load by the unweighted least norm
+    /// lambda, one of many splits that balance, `redundant_rows` says when that happens. Dampers
+    /// act with the joints' current velocities, pause the simulation for a purely static case.
+    pub fn static_forces(&self) -> StaticReport {
+        let jacobian = self.jacobian();
+        let (m, n) = (jacobian.row_count(), jacobian.column_count());
+        let column_of = |id: JointId| jacobian.joints.iter().position(|j| *j == id).map(|i| i * 3);
+
+        let mut applied = vec![0.0f64; n];
+        for (id, force) in self.element_forces() {
+            if let Some(c) = column_of(id) {
+                applied[c] += force.x as f64;
+                applied[c + 1] += force.y as f64;
+                applied[c + 2] += force.z as f64;
+            }
+        }
+
+        // (J J^T) lambda = -J F
+        let j = &jacobian.matrix;
+        let rhs: Vec<f64> = (0..m).map(|r| -dot(&j[r], &applied)).collect();
+        let lambdas = row_space_solve(j, &rhs);
+
+        // Each constraint's force on each of its joints
+        let mut contributions: Vec<(usize, JointId, Vec3)> = Vec::new();
+        for ((row, owner), lambda) in jacobian.rows.iter().zip(&jacobian.owners).zip(&lambdas) {
+            for (id, gradient) in &row.gradient {
+                let force = *gradient * *lambda as f32;
+                match contributions.iter_mut().find(|(o, j, _)| o == owner && j == id) {
+                    Some((_, _, sum)) => *sum += force,
+                    None => contributions.push((*owner, *id, force)),
+                }
+            }
+        }
+
+        // constraints reaching a joint that can move carry load from the mechanism into the ground
+        let grounded = |id: &JointId| self.joints.get(*id).is_none_or(|joint| joint.inv_mass == 0.0);
+        let moving_owners: Vec<usize> = contributions.iter()
+            .filter(|(_, id, _)| !grounded(id))
+            .map(|(owner, _, _)| *owner)
+            .collect();
+        let mut unbalanced = 0.0f32;
+        let reactions = jacobian.joints.iter().enumerate()
+            .map(|(i, id)| {
+                let through: Vec<&(usize, JointId, Vec3)> = contributions.iter().filter(|(_, j, _)| j == id).collect();
+                let bearing = through.iter().map(|(_, _, f)| f.length()).fold(0.0, f32::max);
+                let net: Vec3 = through.iter().map(|(_, _, f)| *f).sum();
+                let load = Vec3::new(applied[i * 3] as f32, applied[i * 3 + 1] as f32, applied[i * 3 + 2] as f32);
+                unbalanced = unbalanced.max((load + net).length());
+                let support = if grounded(id) {
+                    let mechanism: Vec3 = through.iter()
+                        .filter(|(o, _, _)| moving_owners.contains(o))
+                        .map(|(_, _, f)| *f)
+                        .sum();
+                    -(load + mechanism)
+                } else {
+                    Vec3::ZERO
+                };
+                JointReaction { joint_id: *id, support, bearing }
+            })
+            .collect();
+
+        // The first row of a distance constraint has d|b - a|/db = direction, its lambda pushes b away
+        let mut link_loads = Vec::new();
+        for (link_id, link) in &self.links {
+            let [a, b] = link.joints[..] else {
+                continue;
+            };
+            let tension = self.constraints.iter().enumerate()
+                .filter_map(|(index, c)| c.as_any().downcast_ref::<DistanceConstraint>().map(|d| (index, d)))
+                .find(|(_, d)| (d.joint_a == a && d.joint_b == b) || (d.joint_a == b && d.joint_b == a))
+                .and_then(|(index, _)| jacobian.owners.iter().position(|o| *o == index))
+                .map(|row| -lambdas[row] as f32);
+            link_loads.extend(tension.map(|tension| (link_id, tension)));
+        }
+
+        // A driver's first row is d(angle)/dx (or travel), its lambda is the generalized force
+        let driver_loads = self.constraints.iter().enumerate()
+            .filter(|(_, c)| {
+                let any = c.as_any();
+                any.is::<CrankDriverConstraint>()
+                    || any.is::<LinearActuatorConstraint>()
+                    || any.downcast_ref::<FixedAngleConstraint>().is_some_and(|angle| angle.drive.is_some())
+            })
+            .filter_map(|(index, _)| {
+                let row = jacobian.owners.iter().position(|o| *o == index)?;
+                Some((index, lambdas[row] as f32))
+            })
+            .collect();
+
+        let mechanism_rows: Matrix = j.iter().zip(&jacobian.owners)
+            .filter(|(_, owner)| moving_owners.contains(owner))
+            .map(|(row, _)| row.clone())
+            .collect();
+        let (_, independent) = row_basis(&mechanism_rows, 1e-6);
+        let redundant_rows = independent.iter().filter(|independent| !**independent).count();
+
+        StaticReport { reactions, link_loads, driver_loads, unbalanced, redundant_rows }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+
+    fn build(src: &str) -> Simulation {
+        DslCompiler::compile_to_simulation(UgokuParser::parse_dsl(src).unwrap()).unwrap()
+    }
+
+    const BRACKET: &str = "sim bracket {
+    joint a(0,0,0)
+    joint c(0,1,0)
+    joint b(1,0,0)
+    link boom(a,b)
+    link tie(c,b)
+    distance(a,b,1)
+    distance(c,b,1.41421356)
+    fixed(a,c)
+    plane((a,b,c), Z)
+    load(b, (0,-10,0))
+}";
+
+    #[test]
+    fn bracket_takes_its_load_into_the_ground() {
+        let sim = build(BRACKET);
+        let report = sim.static_forces();
+        assert!(report.unbalanced < 1e-3);
+        assert_eq!(report.redundant_rows, 0);
+
+        let support = |name: &str| {
+            let reaction = report.reactions.iter().find(|r| sim.joints[r.joint_id].name == name).unwrap();
+            reaction.support
+        };
+        assert!(support("a").distance(Vec3::new(10.0, 0.0, 0.0)) < 1e-2, "{:?}", support("a"));
+        assert!(support("c").distance(Vec3::new(-10.0, 10.0, 0.0)) < 1e-2, "{:?}", support("c"));
+        assert_eq!(support("b"), Vec3::ZERO);
+
+        // the tie pulls, the boom pushes
+        let tensions: Vec<f32> = report.link_loads.iter().map(|(_, tension)| *tension).collect();
+        assert!((tensions[0] + 10.0).abs() < 1e-2 && (tensions[1] - 14.142).abs() < 1e-2, "{tensions:?}");
+    }
+
+    #[test]
+    fn driver_holds_the_crank_and_redundancy_is_flagged() {
+        let sim = build(include_str!("../examples/crankload.ugoku"));
+        let report = sim.static_forces();
+        assert!(report.unbalanced < 1e-3);
+        let supports: Vec3 = report.reactions.iter().map(|r| r.support).sum();
+        assert!(supports.distance(Vec3::new(0.0, 20.0, 0.0)) < 1e-2, "{supports:?}");
+        assert_eq!(report.driver_loads.len(), 1);
+        assert!(report.driver_loads[0].1.abs() > 1.0);
+
+        let twice = build(&BRACKET.replace("load(", "distance(a,b,1)\n    load("));
+        assert_eq!(twice.static_forces().redundant_rows, 1);
+    }
+}
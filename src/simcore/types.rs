@@ -1,6 +1,8 @@
 use generational_arena::{Arena as GenArena, Index};
-use glam::{Vec2, Vec3}; // Add Vec3
+use glam::{Quat, Vec2, Vec3}; // Add Vec3
 use std::any::Any;
+use crate::simcore::solvers::Solver;
+use crate::simcore::solvers::solvers::project_rows;
 
 pub type JointId = Index;
 pub type LinkId = Index;
@@ -10,34 +12,266 @@ pub struct Simulation {
     pub joints: GenArena<Joint>,
     pub links: GenArena<Link>,
     pub constraints: Vec<Box<dyn Constraint>>,
+    pub forces: Vec<Box<dyn ForceElement>>,
+    pub time: f32,
+    pub damping: f32, // fraction of velocity lost per second
+    pub solver: Box<dyn Solver>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Joint {
+    pub name: String,
     pub position: Position, // Changed from Vec2 to Position
     pub joint_type: JointType,
     pub connected_links: Vec<LinkId>,
+    pub velocity: Vec3,
+    pub inv_mass: f32, // 0.0 means the joint never moves under dynamics
 }
 
+impl Joint {
+    pub fn mass(&self) -> f32 {
+        if self.inv_mass > 0.0 { 1.0 / self.inv_mass } else { f32::INFINITY }
+    }
+    pub fn set_mass(&mut self, mass: f32) {
+        self.inv_mass = if mass.is_finite() && mass > 0.0 { 1.0 / mass } else { 0.0 };
+    }
+}
+
+/// How the links meeting at a joint move against each other. The first link at the joint
+/// carries the axes, a joint on a single link moves against the world. Slide lines and planes
+/// go through the joint's starting position and stay fixed in the world.
 #[derive(Debug, Clone)]
 pub enum JointType {
+    /// Pinned to the ground
     Fixed,
-    Revolute,
-    Slider { axis: Vec2 },
+    /// Hinge, the links turn about `axis` within the angle limits
+    Revolute { axis: Vec3, limits: Option<(f32, f32)> },
+    /// Pin on a slider block, the joint runs along `axis` within the travel limits
+    Slider { axis: Vec3, limits: Option<(f32, f32)> },
+    /// Ball, the links turn freely, at most `cone` away from where they started
+    Spherical { cone: Option<f32> },
+    /// Cardan cross, turning about `axis_a` and then `axis_b`, each by at most `limit`.
+    /// Two joint links have no spin, so without a limit this moves like a ball joint
+    Universal { axis_a: Vec3, axis_b: Vec3, limit: Option<f32> },
+    /// Slides along and turns about `axis`, within the travel limits
+    Cylindrical { axis: Vec3, limits: Option<(f32, f32)> },
+    /// Slides in the plane normal to `normal` and turns about it
+    Planar { normal: Vec3 },
+}
+
+impl Default for JointType {
+    // a bare joint only keeps its links together
+    fn default() -> Self {
+        JointType::Spherical { cone: None }
+    }
 }
 #[derive(Debug, Clone, PartialEq)]
 pub struct Link {
     pub joints: Vec<JointId>,
     pub rigid: bool,
+    pub collision: Option<Capsule>,
+}
+
+/// Collision volume of a link, a capsule of `radius` around each edge. Only capsules on the
+/// same layer touch, links stacked on different layers pass over each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capsule {
+    pub radius: f32,
+    pub layer: u32,
 }
 
 
+/// The `xpbd` and `xpbd_mut` getters of a constraint that keeps its XPBD state in an `xpbd` field
+macro_rules! xpbd_field {
+    () => {
+        fn xpbd(&self) -> &XpbdState {
+            &self.xpbd
+        }
+
+        fn xpbd_mut(&mut self) -> &mut XpbdState {
+            &mut self.xpbd
+        }
+    };
+}
+pub(crate) use xpbd_field;
+
 pub trait Constraint: std::fmt::Debug + Any + Send + Sync + 'static {
-    fn apply(&self, sim: &mut Simulation);
-    fn is_satisfied(&self, sim: &Simulation) -> bool;
+    /// One PBD correction, a mass weighted projection of the rows by default
+    fn apply(&self, sim: &mut Simulation) {
+        let rows = self.rows(sim);
+        project_rows(sim, &rows);
+    }
+
+    fn is_satisfied(&self, sim: &Simulation) -> bool {
+        self.error(sim) < 1e-5
+    }
+
     fn as_any(&self) -> &dyn Any;
 
+    /// Scalar equations C(x) = 0 with their gradients, evaluated at the current pose.
+    /// Inactive rows (e.g. a limit that is not hit) are simply left out.
+    fn rows(&self, sim: &Simulation) -> Vec<ConstraintRow>;
+    fn xpbd(&self) -> &XpbdState;
+    fn xpbd_mut(&mut self) -> &mut XpbdState;
+
+    /// Scalar residual, 0.0 when satisfied. Largest row error by default.
+    fn error(&self, sim: &Simulation) -> f32 {
+        self.rows(sim).iter().map(|row| row.value.abs()).fold(0.0, f32::max)
+    }
+
+    /// Short name for reports and UI
+    fn type_name(&self) -> &'static str {
+        let full = std::any::type_name::<Self>();
+        full.rsplit("::").next().unwrap_or(full)
+    }
 
+    /// Constraint force magnitude from the lambdas of the last step.
+    fn force(&self, dt: f32) -> f32 {
+        if dt <= 0.0 {
+            return 0.0;
+        }
+        let lambda_sq: f32 = self.xpbd().lambdas.iter().map(|l| l * l).sum();
+        lambda_sq.sqrt() / (dt * dt)
+    }
+
+    /// Called once the pose of a step is solved, for constraints that carry state
+    /// from one step to the next (e.g. unwrapped rotation). Nothing by default.
+    fn advance(&mut self, _sim: &Simulation) {}
+}
+
+/// Pushes on joints instead of holding them, only felt by a dynamic step
+pub trait ForceElement: std::fmt::Debug + Any + Send + Sync + 'static {
+    /// Force on every joint it touches, at the current pose and velocities
+    fn forces(&self, sim: &Simulation) -> Vec<(JointId, Vec3)>;
+    fn as_any(&self) -> &dyn Any;
+
+    /// Energy stored at the current pose, 0.0 for elements that store none
+    fn energy(&self, _sim: &Simulation) -> f32 {
+        0.0
+    }
+
+    /// Short name for reports and UI
+    fn type_name(&self) -> &'static str {
+        let full = std::any::type_name::<Self>();
+        full.rsplit("::").next().unwrap_or(full)
+    }
+}
+
+/// Spring and damper in parallel along the line between two joints
+#[derive(Debug, Clone)]
+pub struct LinearSpring {
+    pub joint_a: JointId,
+    pub joint_b: JointId,
+    pub rest_length: f32,
+    pub stiffness: f32, // force per unit of stretch
+    pub damping: f32,   // force per unit of stretching speed
+}
+
+/// Spring and damper in parallel on the signed angle from arm A to arm B about `axis` through the pivot
+#[derive(Debug, Clone)]
+pub struct TorsionalSpring {
+    pub joint_a: JointId,
+    pub pivot: JointId,
+    pub joint_b: JointId,
+    pub axis: Vec3,
+    pub rest_angle: f32,
+    pub stiffness: f32, // torque per radian
+    pub damping: f32,   // torque per radian per second
+}
+
+/// Uniform acceleration on every joint that has mass
+#[derive(Debug, Clone)]
+pub struct Gravity {
+    pub acceleration: Vec3,
+}
+
+/// Constant force on one joint, fixed in the world
+#[derive(Debug, Clone)]
+pub struct PointLoad {
+    pub joint_id: JointId,
+    pub force: Vec3,
+}
+
+/// Constant torque about `axis` through the pivot, turning the arm and reacting on the pivot
+#[derive(Debug, Clone)]
+pub struct AppliedTorque {
+    pub pivot: JointId,
+    pub arm: JointId,
+    pub axis: Vec3,
+    pub torque: f32,
+}
+
+/// One scalar constraint equation and dC/dx for every joint it touches
+#[derive(Debug, Clone)]
+pub struct ConstraintRow {
+    pub value: f32,
+    pub gradient: Vec<(JointId, Vec3)>,
+    pub unilateral: bool, // C >= 0 rather than C = 0, the row can push but never pull
+    pub key: Option<u64>, // identity of a row that comes and goes, XPBD keeps its lambda with it
+}
+
+impl ConstraintRow {
+    pub fn new(value: f32, gradient: Vec<(JointId, Vec3)>) -> Self {
+        Self { value, gradient, unilateral: false, key: None }
+    }
+
+    /// Tell apart rows of one constraint that only show up some of the time.
+    /// A limit row keeps which end of its range it is on.
+    pub fn keyed(mut self, key: u64) -> Self {
+        self.key = Some(key << 1 | self.key.unwrap_or(0));
+        self
+    }
+
+    /// Stop keeping `measure` inside [min, max], `gradient` is d(measure)/dx. Only a limit that is
+    /// being hit produces a row, turned around as needed so it reads C >= 0. A constraint with
+    /// more than one limit keys them apart with `keyed`.
+    pub fn limit(measure: f32, (min, max): (f32, f32), gradient: Vec<(JointId, Vec3)>) -> Option<Self> {
+        let (value, sign, end) = if measure < min {
+            (measure - min, 1.0, 0)
+        } else if measure > max {
+            (max - measure, -1.0, 1)
+        } else {
+            return None;
+        };
+        let gradient = gradient.into_iter().map(|(id, grad)| (id, grad * sign)).collect();
+        Some(Self { value, gradient, unilateral: true, key: Some(end) })
+    }
+}
+
+/// Outcome of a solve, tells a converged pose apart from one that can't be assembled
+#[derive(Debug, Clone, Default)]
+pub struct SolveReport {
+    pub iterations: usize,
+    pub max_iterations: usize, // the cap the solve ran under
+    pub max_residual: f32,
+    pub violated: Vec<(usize, f32)>, // (index into Simulation::constraints, residual)
+    pub converged: bool,
+}
+
+/// Per constraint XPBD state
+#[derive(Debug, Clone, Default)]
+pub struct XpbdState {
+    pub compliance: f32, // inverse stiffness, 0.0 is rigid
+    pub lambdas: Vec<f32>, // accumulated over one step, one per row
+    pub keys: Vec<Option<u64>>, // key of the row each lambda belongs to
+}
+
+impl XpbdState {
+    /// Lambdas lined up with `rows`. A keyed row gets the lambda of the row with its key, unkeyed
+    /// rows are always there and take theirs in order, a row not seen before starts at zero.
+    pub fn matched(&self, rows: &[ConstraintRow]) -> Vec<f32> {
+        let mut unkeyed = self.keys.iter().zip(&self.lambdas)
+            .filter(|(key, _)| key.is_none())
+            .map(|(_, lambda)| *lambda);
+        rows.iter()
+            .map(|row| match row.key {
+                Some(key) => self.keys.iter().zip(&self.lambdas)
+                    .find(|(k, _)| **k == Some(key))
+                    .map_or(0.0, |(_, lambda)| *lambda),
+                None => unkeyed.next().unwrap_or(0.0),
+            })
+            .collect()
+    }
 }
 
 // Add Position enum
@@ -61,9 +295,11 @@ impl Position {
             Position::Vec3(v) => v.truncate(),
         }
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn add(self, rhs: Position) -> Position {
         Position::Vec3(self.as_vec3() + rhs.as_vec3())
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn sub(self, rhs: Position) -> Position {
         Position::Vec3(self.as_vec3() - rhs.as_vec3())
     }
@@ -87,7 +323,8 @@ impl Position {
 #[derive(Debug, Clone)]
 pub struct FixedPositionConstraint {
     pub joint_id: JointId,
-    pub target_position: Position, 
+    pub target_position: Position,
+    pub xpbd: XpbdState,
 }
 
 #[derive(Debug, Clone)]
@@ -95,6 +332,17 @@ pub struct DistanceConstraint {
     pub joint_a: JointId,
     pub joint_b: JointId,
     pub target_distance: f32,
+    pub xpbd: XpbdState,
+}
+
+/// Distance free anywhere in [min, max], a rope has min 0
+#[derive(Debug, Clone)]
+pub struct DistanceRangeConstraint {
+    pub joint_a: JointId,
+    pub joint_b: JointId,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub xpbd: XpbdState,
 }
 
 #[derive(Debug, Clone)]
@@ -102,6 +350,7 @@ pub struct PlaneConstraint {
     pub joint_id: JointId,
     pub normal: Vec3,
     pub plane_point: Vec3,
+    pub xpbd: XpbdState,
 }
 
 
@@ -111,6 +360,8 @@ pub struct PrismaticConstraintVector {
     pub joint_id: JointId,
     pub axis: Vec3, //normalize the jawn
     pub origin: Vec3,
+    pub limits: Option<(f32, f32)>, // travel along the axis from the origin
+    pub xpbd: XpbdState,
 }
 
 #[derive(Debug, Clone)]
@@ -118,7 +369,7 @@ pub struct PrismaticConstraintLink {
     pub joint_id: JointId,
     pub link_id: LinkId,
     pub origin: Vec3,
-
+    pub xpbd: XpbdState,
 }
 
 #[derive(Debug, Clone)]
@@ -126,15 +377,303 @@ pub struct FixedAngleConstraint {
     pub joint_a_id: JointId,    // First joint of link A (not the pivot)
     pub joint_b_id: JointId,    // First joint of link B (not the pivot)
     pub pivot_joint_id: JointId, // The shared pivot joint
-    pub target_angle: f32,   // in radians
+    pub axis: Vec3,          // angles are measured counterclockwise about this axis, from A to B
+    pub target_angle: f32,   // in radians, signed
+    pub drive: Option<MotionProfile>, // drives the target angle over time instead
+    pub xpbd: XpbdState,
 }
 
 
+/// Hinge about `axis` through the pivot, the moving joint stays in the plane normal to the axis
+/// (at `offset` along it) and its signed angle from `zero_direction` stays within the limits.
+/// The axis and zero direction turn with `frame`.
 #[derive(Debug, Clone)]
 pub struct RevoluteConstraint{
     pub pivot_joint_id: JointId, 
     pub moving_joint_id: JointId,   
-    pub rest_direction: Vec3, 
+    pub axis: Vec3,
+    pub zero_direction: Vec3, // perpendicular to the axis
+    pub offset: f32,
+    pub frame: CarriedFrame,
     pub min_angle: f32, 
-    pub max_angle: f32, 
-}
\ No newline at end of file
+    pub max_angle: f32,
+    pub xpbd: XpbdState,
+}
+
+/// Keeps every pair of capsules from overlapping, a row only for the pairs that do
+#[derive(Debug, Clone, Default)]
+pub struct ContactConstraint {
+    pub xpbd: XpbdState,
+}
+
+/// Frame carried by a link, turned by the smallest rotation that follows the link's direction
+/// (a two joint link has no spin of its own). Without a link it stays fixed in the world.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CarriedFrame {
+    pub link: Option<(JointId, JointId)>,
+    pub direction: Vec3, // direction of the link when the frame was last rebased
+}
+
+/// Ball joint limit, the arm stays within `max_angle` of `rest_direction`, turned with `frame`
+#[derive(Debug, Clone)]
+pub struct ConeConstraint {
+    pub pivot_joint_id: JointId,
+    pub moving_joint_id: JointId,
+    pub rest_direction: Vec3,
+    pub max_angle: f32,
+    pub frame: CarriedFrame,
+    pub xpbd: XpbdState,
+}
+
+/// Universal joint limits. The arm is turned by `alpha` about `axis_a` and `beta` about `axis_b`
+/// (kept normal to `axis_a`), each stays within `limit` of its rest angle.
+#[derive(Debug, Clone)]
+pub struct UniversalConstraint {
+    pub pivot_joint_id: JointId,
+    pub moving_joint_id: JointId,
+    pub axis_a: Vec3,
+    pub axis_b: Vec3,
+    pub rest: (f32, f32),
+    pub limit: f32,
+    pub frame: CarriedFrame,
+    pub xpbd: XpbdState,
+}
+
+/// Motor driver, the moving joint's angle about the pivot follows `profile` over time.
+/// Angles are measured about `axis` from `zero_direction`.
+#[derive(Debug, Clone)]
+pub struct CrankDriverConstraint {
+    pub pivot_joint_id: JointId,
+    pub moving_joint_id: JointId,
+    pub axis: Vec3,
+    pub zero_direction: Vec3, // perpendicular to axis
+    pub profile: MotionProfile,
+    pub xpbd: XpbdState,
+}
+
+/// Rigid body over any number of joints by shape matching: the rest shape is fitted onto the
+/// joints (best rotation about the mass centre) and PBD moves every joint to its fitted spot.
+/// Rows are the distances of `rigid_pairs`, so the other backends and the analysis see the same rigid body.
+#[derive(Debug, Clone)]
+pub struct ShapeMatchConstraint {
+    pub joints: Vec<JointId>,
+    pub rest: Vec<Vec3>, // joint positions when the body was made
+    pub rotation: Quat, // last fit, warm start for the next one
+    pub xpbd: XpbdState,
+}
+
+/// Gear pair, rotation of arm B about pivot B is `ratio` times the rotation of arm A about pivot A.
+/// Negative ratio for external meshing, positive for an internal (ring) gear.
+/// Rotation is measured from the reference arm directions, which follow the gears every step.
+#[derive(Debug, Clone)]
+pub struct GearConstraint {
+    pub pivot_a: JointId,
+    pub arm_a: JointId,
+    pub pivot_b: JointId,
+    pub arm_b: JointId,
+    pub axis: Vec3, // shared by both gears
+    pub ratio: f32,
+    pub reference_a: Vec3,
+    pub reference_b: Vec3,
+    pub phase: f32, // error left over when the references were last moved
+    pub xpbd: XpbdState,
+}
+
+/// Rack and pinion, the rack joint slides `pitch_radius` along `direction` per radian the pinion
+/// arm turns about `pivot`. Like the gear, both are measured from references that follow every step.
+#[derive(Debug, Clone)]
+pub struct RackPinionConstraint {
+    pub pivot: JointId,
+    pub arm: JointId,
+    pub rack: JointId,
+    pub axis: Vec3, // pinion rotation axis
+    pub direction: Vec3, // rack travel for positive pinion rotation
+    pub pitch_radius: f32,
+    pub reference_arm: Vec3,
+    pub reference_offset: f32, // rack position along direction, relative to the pivot
+    pub phase: f32,
+    pub xpbd: XpbdState,
+}
+
+/// Lead screw / helical joint, the nut advances `lead` along the screw axis per turn of the arm.
+/// Negative lead for a left handed thread.
+#[derive(Debug, Clone)]
+pub struct ScrewConstraint {
+    pub pivot: JointId, // on the screw axis
+    pub arm: JointId,
+    pub nut: JointId,
+    pub axis: Vec3,
+    pub lead: f32,
+    pub reference_arm: Vec3,
+    pub reference_offset: f32,
+    pub phase: f32,
+    pub xpbd: XpbdState,
+}
+
+/// Belt looped over two or more pulleys in order, no slip so every pulley sees the same belt travel.
+/// Open belts wrap every pulley counter clockwise when the pulleys are listed counter clockwise,
+/// crossed belts alternate the wrap direction from one pulley to the next.
+#[derive(Debug, Clone)]
+pub struct BeltConstraint {
+    pub pulleys: Vec<Pulley>,
+    pub axis: Vec3,
+    pub crossed: bool,
+    pub phases: Vec<f32>, // one per pair of neighbouring pulleys
+    pub xpbd: XpbdState,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pulley {
+    pub pivot: JointId,
+    pub arm: JointId, // turns with the pulley
+    pub radius: f32,
+    pub reference_arm: Vec3,
+}
+
+/// Keeps a joint on a curve. The curve is either fixed in the world or rides on a link,
+/// in which case it is stored in the link frame (see `PointOnCurveConstraint::frame`).
+#[derive(Debug, Clone)]
+pub struct PointOnCurveConstraint {
+    pub joint_id: JointId,
+    pub curve: PathCurve,
+    pub link: Option<(JointId, JointId)>, // link origin joint, joint giving its local x
+    pub axis: Vec3, // link frame z, the link turns about it
+    pub xpbd: XpbdState,
+}
+
+/// Pin in a slot cut into a link. The slot is a straight or arc centre line in the link frame,
+/// x from the link's first joint towards its second, y = axis x x, with stops at both ends.
+#[derive(Debug, Clone)]
+pub struct PinInSlotConstraint {
+    pub pin: JointId,
+    pub origin: JointId,
+    pub arm: JointId,
+    pub axis: Vec3,
+    pub slot: PathCurve,
+    pub xpbd: XpbdState,
+}
+
+/// Open parametric curve, u from 0 to 1
+#[derive(Debug, Clone)]
+pub enum PathCurve {
+    /// `radius` is the vector from the centre to the start, turned `sweep` radians about `normal`
+    Arc { centre: Vec3, radius: Vec3, normal: Vec3, sweep: f32 },
+    Polyline { points: Vec<Vec3> },
+    /// One Bezier segment, the degree is the number of points minus one
+    Bezier { points: Vec<Vec3> },
+    /// Catmull-Rom through every point
+    CatmullRom { points: Vec<Vec3> },
+}
+
+/// Cam and follower, the follower (or the centre of a roller of `roller_radius` on it) stays
+/// `roller_radius` away from the cam profile. The cam turns about `pivot` with the direction
+/// to `arm` as its local x axis, the profile is drawn in that frame.
+#[derive(Debug, Clone)]
+pub struct CamConstraint {
+    pub pivot: JointId,
+    pub arm: JointId,
+    pub follower: JointId,
+    pub axis: Vec3,
+    pub profile: CamProfile,
+    pub roller_radius: f32, // 0 for a knife edge follower
+    pub xpbd: XpbdState,
+}
+
+/// Closed cam outline in the cam's own frame, traversed counter clockwise
+#[derive(Debug, Clone)]
+pub enum CamProfile {
+    /// Base circle plus lift tabulated over cam angle, (angle, lift), smooth and periodic in between
+    RadialLift { base_radius: f32, lift: Vec<(f32, f32)> },
+    /// r(angle) = base_radius + sum of a_k cos(k angle) + b_k sin(k angle), harmonics are (a_k, b_k) from k = 1
+    Polar { base_radius: f32, harmonics: Vec<(f32, f32)> },
+    /// Closed Catmull-Rom spline through the points
+    Spline { points: Vec<Vec2> },
+}
+
+/// Driven prismatic joint, the joint slides along `axis` from `origin` by `profile` over time
+/// (lead screw, belt axis)
+#[derive(Debug, Clone)]
+pub struct LinearActuatorConstraint {
+    pub joint_id: JointId,
+    pub axis: Vec3, // normalized
+    pub origin: Vec3, // displacement 0
+    pub profile: MotionProfile,
+    pub xpbd: XpbdState,
+}
+
+/// Displacement (angle or distance) as a function of time
+#[derive(Debug, Clone)]
+pub enum MotionProfile {
+    ConstantSpeed { speed: f32 },
+    Keyframes { points: Vec<(f32, f32)> }, // (time, value), linear in between, held outside
+    /// Accelerate, cruise, decelerate over `distance`, triangular if max_speed is never reached
+    Trapezoidal { distance: f32, max_speed: f32, acceleration: f32 },
+    /// Cycloidal move over `distance`, smooth acceleration so jerk stays bounded
+    SCurve { distance: f32, max_speed: f32 },
+}
+
+impl MotionProfile {
+    pub fn value(&self, time: f32) -> f32 {
+        match self {
+            MotionProfile::ConstantSpeed { speed } => speed * time,
+            MotionProfile::Keyframes { points } => {
+                let (Some(first), Some(last)) = (points.first(), points.last()) else {
+                    return 0.0;
+                };
+                if time <= first.0 {
+                    return first.1;
+                }
+                if time >= last.0 {
+                    return last.1;
+                }
+                for pair in points.windows(2) {
+                    let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+                    if time <= t1 {
+                        let span = t1 - t0;
+                        let u = if span > 0.0 { (time - t0) / span } else { 1.0 };
+                        return v0 + (v1 - v0) * u;
+                    }
+                }
+                last.1
+            }
+            MotionProfile::Trapezoidal { distance, max_speed, acceleration } => {
+                let (d, v, a) = (distance.abs(), max_speed.abs(), acceleration.abs());
+                if d == 0.0 || v == 0.0 || a == 0.0 {
+                    return 0.0;
+                }
+                let mut ramp_time = v / a;
+                let mut peak_speed = v;
+                if a * ramp_time * ramp_time > d {
+                    // never reaches max_speed
+                    ramp_time = (d / a).sqrt();
+                    peak_speed = a * ramp_time;
+                }
+                let ramp_distance = 0.5 * a * ramp_time * ramp_time;
+                let cruise_time = (d - 2.0 * ramp_distance) / peak_speed;
+                let t = time.max(0.0);
+
+                let s = if t < ramp_time {
+                    0.5 * a * t * t
+                } else if t < ramp_time + cruise_time {
+                    ramp_distance + peak_speed * (t - ramp_time)
+                } else {
+                    let remaining = (2.0 * ramp_time + cruise_time - t).max(0.0);
+                    d - 0.5 * a * remaining * remaining
+                };
+                s * distance.signum()
+            }
+            MotionProfile::SCurve { distance, max_speed } => {
+                if *max_speed == 0.0 {
+                    return 0.0;
+                }
+                // cycloid peaks at twice the average speed
+                let duration = 2.0 * distance.abs() / max_speed.abs();
+                if duration == 0.0 {
+                    return 0.0;
+                }
+                let u = (time / duration).clamp(0.0, 1.0);
+                distance * (u - (std::f32::consts::TAU * u).sin() / std::f32::consts::TAU)
+            }
+        }
+    }
+}
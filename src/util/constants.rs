@@ -14,10 +14,17 @@ pub const ZOOM_SPEED: f32 = 5.0;
 pub const MIN_ZOOM: f32 = 1.0;
 pub const MAX_ZOOM: f32 = 20.0;
 
+// Simulation pub constants
+pub const MAX_SIM_TIME_STEP: f32 = 1.0 / 30.0; // clamp frame hitches so the integrator stays stable
+pub const SINGULARITY_TOLERANCE: f64 = 0.02; // roughly the sine of the angle between lined up links
+pub const SINGULARITY_CHECK_INTERVAL: f32 = 0.05; // seconds between singularity checks while the pose keeps changing
+
 /// Sim core wrapper types
 #[derive(Resource)]
 pub struct SimWrapper {
     pub sim: Simulation,
+    pub running: bool,
+    pub last_report: SolveReport,
 }
 
 #[derive(Component)]
@@ -187,6 +187,11 @@ pub fn joint_drag_system(
 
     for joint_wrapper in selected_joints.iter() {
         if let Some(joint) = sim_wrapper.sim.joints.get_mut(joint_wrapper.joint_id) {
+            // Ground joints stay put
+            if joint.inv_mass == 0.0 {
+                continue;
+            }
+
             // Get current joint position
             let current_joint_pos = match &joint.position {
                 Position::Vec3(pos) => *pos,
@@ -203,6 +208,7 @@ pub fn joint_drag_system(
                 
                 let new_pos = glam::Vec3::new(intersection.x, intersection.y, intersection.z);
                 joint.position = Position::Vec3(new_pos);
+                joint.velocity = glam::Vec3::ZERO;
                 move_joint_events.write(MoveJoint {
                     joint_id: joint_wrapper.joint_id,
                     new_position: Position::Vec3(new_pos),
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use crate::util::constants::*;
+use crate::simcore::collision::*;
+use crate::simcore::types::*;
+
+#[derive(Resource, Default)]
+pub struct InterferenceState {
+    pub report: InterferenceReport,
+    pub contacts: Vec<Contact>, // clearances at the current pose
+}
+
+/// Name of a link from its joints, links don't carry names of their own
+pub fn link_label(sim: &Simulation, link_id: LinkId) -> String {
+    let Some(link) = sim.links.get(link_id) else {
+        return "?".to_string();
+    };
+    let names: Vec<&str> = link.joints.iter()
+        .filter_map(|id| sim.joints.get(*id).map(|j| j.name.as_str()))
+        .collect();
+    names.join("-")
+}
+
+pub fn interference_record_system(
+    sim_wrapper: Res<SimWrapper>,
+    mut state: ResMut<InterferenceState>,
+) {
+    if !sim_wrapper.is_changed() {
+        return;
+    }
+    let sim = &sim_wrapper.sim;
+    let InterferenceState { report, contacts } = &mut *state;
+    *contacts = sim.clearances();
+    report.record_contacts(contacts, sim.time);
+}
+
+pub fn interference_gizmo_system(
+    sim_wrapper: Res<SimWrapper>,
+    state: Res<InterferenceState>,
+    mut gizmos: Gizmos,
+) {
+    let sim = &sim_wrapper.sim;
+    let to_bevy = |v: glam::Vec3| Vec3::new(v.x, v.y, v.z);
+
+    let touching: Vec<LinkId> = state.contacts.iter()
+        .filter(|contact| contact.clearance <= CONTACT_TOLERANCE)
+        .flat_map(|contact| [contact.links.0, contact.links.1])
+        .collect();
+
+    for (link_id, link) in &sim.links {
+        let Some(capsule) = link.collision else {
+            continue;
+        };
+        let color = if touching.contains(&link_id) {
+            Color::srgb(1.0, 0.2, 0.2)
+        } else {
+            Color::srgba(0.7, 0.7, 0.7, 0.5)
+        };
+        for (start, end) in link_segments(link) {
+            let (Some(start), Some(end)) = (sim.joints.get(start), sim.joints.get(end)) else {
+                continue;
+            };
+            let (start, end) = (to_bevy(start.position.as_vec3()), to_bevy(end.position.as_vec3()));
+            let rotation = Quat::from_rotation_arc(Vec3::Y, (end - start).try_normalize().unwrap_or(Vec3::Y));
+            gizmos.primitive_3d(
+                &Capsule3d::new(capsule.radius, start.distance(end)),
+                Isometry3d::new((start + end) / 2.0, rotation),
+                color,
+            );
+        }
+    }
+}
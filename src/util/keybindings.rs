@@ -15,7 +15,9 @@ pub struct KeyBindings {
     pub mouse_pan: MouseButton,
     pub zoom_in: KeyCode,
     pub zoom_out: KeyCode,
+    pub play_pause: KeyCode,
     pub iterations_per_time_step: usize,
+    pub solver_tolerance: f32,
 }
 
 impl Default for KeyBindings {
@@ -32,7 +34,9 @@ impl Default for KeyBindings {
             mouse_pan: MouseButton::Left,
             zoom_in: KeyCode::Equal,
             zoom_out: KeyCode::Minus,
-            iterations_per_time_step: 20,
+            play_pause: KeyCode::KeyP,
+            iterations_per_time_step: 40,
+            solver_tolerance: 1e-5,
         }
     }
 }
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use crate::util::constants::*;
+use crate::simcore::kinematics::*;
+
+#[derive(Resource)]
+pub struct KinematicsState {
+    pub show: bool,
+    pub report: KinematicsReport,
+    pub peaks: KinematicsPeaks,
+    pub arrow_scale: f32, // seconds, a velocity arrow shows where the joint gets to in this long
+    pending: bool, // the sim moved since the last report
+}
+
+impl Default for KinematicsState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            report: KinematicsReport::default(),
+            peaks: KinematicsPeaks::default(),
+            arrow_scale: 0.2,
+            pending: true,
+        }
+    }
+}
+
+pub fn kinematics_system(
+    sim_wrapper: Res<SimWrapper>,
+    mut state: ResMut<KinematicsState>,
+) {
+    // nothing moves between steps, the last report still holds
+    state.pending |= sim_wrapper.is_changed();
+    if !state.show || !state.pending {
+        return;
+    }
+    state.pending = false;
+    let KinematicsState { report, peaks, .. } = &mut *state;
+    *report = sim_wrapper.sim.kinematics();
+    peaks.record(report);
+}
+
+pub fn kinematics_gizmo_system(
+    sim_wrapper: Res<SimWrapper>,
+    state: Res<KinematicsState>,
+    mut gizmos: Gizmos,
+) {
+    if !state.show {
+        return;
+    }
+    let sim = &sim_wrapper.sim;
+    let to_bevy = |v: glam::Vec3| Vec3::new(v.x, v.y, v.z);
+
+    // velocity cyan, acceleration magenta, both from the joint
+    for motion in &state.report.joints {
+        let Some(joint) = sim.joints.get(motion.joint_id) else {
+            continue;
+        };
+        let start = to_bevy(joint.position.as_vec3());
+        if motion.velocity.length() > 1e-4 {
+            gizmos.arrow(start, start + to_bevy(motion.velocity) * state.arrow_scale, Color::srgb(0.2, 0.9, 0.9));
+        }
+        if motion.acceleration.length() > 1e-4 {
+            let scale = state.arrow_scale * state.arrow_scale;
+            gizmos.arrow(start, start + to_bevy(motion.acceleration) * scale, Color::srgb(0.9, 0.2, 0.9));
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+use crate::util::constants::*;
+use crate::simcore::analysis::*;
+
+/// Mobility of the current pose, worked out when the panel asks for it
+#[derive(Resource, Default)]
+pub struct MobilityState {
+    pub report: Option<MobilityReport>, // None until asked for, and again once the sim moves
+}
+
+pub fn mobility_invalidate_system(
+    sim_wrapper: Res<SimWrapper>,
+    mut state: ResMut<MobilityState>,
+) {
+    if sim_wrapper.is_changed() && state.report.is_some() {
+        state.report = None;
+    }
+}
@@ -3,4 +3,9 @@ pub mod constants;
 pub mod world;
 pub mod simulation;
 pub  mod interact;
-pub mod keybindings;
\ No newline at end of file
+pub mod keybindings;
+pub mod singularity;
+pub mod interference;
+pub mod mobility;
+pub mod statics;
+pub mod kinematics;
\ No newline at end of file
@@ -1,8 +1,11 @@
 
 use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
 use crate::util::interact::MoveJoint;
 use crate::util::constants::*;
 use crate::util::keybindings::KeyBindings;
+use crate::util::camera::InputFocus;
 use crate::simcore::types::*;
 
 //render
@@ -40,6 +43,14 @@ pub fn render_sim(
 
     let link_mesh = meshes.add(Cuboid::new(0.05, 0.05, 1.0));
 
+    let plate_material = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.2, 0.4, 1.0, 0.6),
+        alpha_mode: AlphaMode::Blend,
+        double_sided: true,
+        cull_mode: None,
+        ..Default::default()
+    });
+
     for (joint_id, joint) in &sim.joints {
         let joint_position = match joint.position {
             Position::Vec2(v) => Vec3::new(v.x, 0.0, v.y),
@@ -85,10 +96,55 @@ pub fn render_sim(
                     link_id: link.0,
                 },
             ));
+        } else if link_data.joints.len() > 2 {
+            // bodies are drawn as a filled plate through their joints
+            let points: Vec<Vec3> = link_data.joints.iter()
+                .filter_map(|id| sim.joints.get(*id))
+                .map(|joint| {
+                    let v = joint.position.as_vec3();
+                    Vec3::new(v.x, v.y, v.z)
+                })
+                .collect();
+            commands.spawn((
+                Mesh3d(meshes.add(plate_mesh(&points))),
+                MeshMaterial3d(plate_material.clone()),
+                Transform::default(),
+                LinkWrapper {
+                    link_id: link.0,
+                },
+            ));
         }
     }
 }
 
+/// Triangle fan around the centroid, vertex 0 is the centroid
+fn plate_mesh(points: &[Vec3]) -> Mesh {
+    let (positions, normals) = plate_vertices(points);
+    let n = points.len() as u32;
+    let indices = (0..n).flat_map(|i| [0, i + 1, (i + 1) % n + 1]).collect();
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+fn plate_vertices(points: &[Vec3]) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let centroid = points.iter().copied().sum::<Vec3>() / points.len() as f32;
+    // Newell's method, fine for slightly bent plates too
+    let normal = (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y))
+        })
+        .sum::<Vec3>()
+        .normalize_or_zero();
+
+    let positions = std::iter::once(centroid).chain(points.iter().copied()).map(|p| p.to_array()).collect();
+    let normals = vec![normal.to_array(); points.len() + 1];
+    (positions, normals)
+}
+
 
 pub fn update_link_visuals(
     sim_wrapper: Res<SimWrapper>,
@@ -122,6 +178,35 @@ pub fn update_link_visuals(
     }
 }
 
+pub fn update_plate_visuals(
+    sim_wrapper: Res<SimWrapper>,
+    plate_query: Query<(&Mesh3d, &LinkWrapper)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let sim = &sim_wrapper.sim;
+
+    for (mesh, link_wrapper) in plate_query.iter() {
+        let Some(link) = sim.links.get(link_wrapper.link_id) else {
+            continue;
+        };
+        if link.joints.len() <= 2 {
+            continue;
+        }
+        let points: Vec<Vec3> = link.joints.iter()
+            .filter_map(|id| sim.joints.get(*id))
+            .map(|joint| {
+                let v = joint.position.as_vec3();
+                Vec3::new(v.x, v.y, v.z)
+            })
+            .collect();
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            let (positions, normals) = plate_vertices(&points);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        }
+    }
+}
+
 pub fn update_joint_visuals(
     sim_wrapper: Res<SimWrapper>,
     mut joint_query: Query<(&mut Transform, &JointWrapper)>,
@@ -138,16 +223,180 @@ pub fn update_joint_visuals(
 
 
 
+/// Outlines for constraints and force elements that have no mesh of their own
+pub fn constraint_gizmo_system(
+    sim_wrapper: Res<SimWrapper>,
+    mut gizmos: Gizmos,
+) {
+    let sim = &sim_wrapper.sim;
+    let to_bevy = |v: glam::Vec3| Vec3::new(v.x, v.y, v.z);
+
+    for constraint in &sim.constraints {
+        if let Some(gear) = constraint.as_any().downcast_ref::<GearConstraint>() {
+            let Some((radius_a, radius_b)) = gear.pitch_radii(sim) else {
+                continue;
+            };
+            let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(gear.axis));
+            for (pivot, radius) in [(gear.pivot_a, radius_a), (gear.pivot_b, radius_b)] {
+                if let Some(joint) = sim.joints.get(pivot) {
+                    let centre = to_bevy(joint.position.as_vec3());
+                    gizmos.circle(Isometry3d::new(centre, rotation), radius, Color::srgb(0.2, 0.8, 0.4));
+                }
+            }
+        }
+
+        if let Some(belt) = constraint.as_any().downcast_ref::<BeltConstraint>() {
+            let color = Color::srgb(0.3, 0.3, 0.3);
+            let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(belt.axis));
+            for pulley in &belt.pulleys {
+                if let Some(joint) = sim.joints.get(pulley.pivot) {
+                    gizmos.circle(Isometry3d::new(to_bevy(joint.position.as_vec3()), rotation), pulley.radius, color);
+                }
+            }
+            for (start, end) in belt.spans(sim) {
+                gizmos.line(to_bevy(start), to_bevy(end), color);
+            }
+        }
+
+        if let Some(on_curve) = constraint.as_any().downcast_ref::<PointOnCurveConstraint>() {
+            if let Some(curve) = on_curve.world_curve(sim) {
+                gizmos.linestrip(curve.samples(64).into_iter().map(to_bevy), Color::srgb(0.6, 0.6, 0.9));
+            }
+        }
+
+        if let Some(slot) = constraint.as_any().downcast_ref::<PinInSlotConstraint>() {
+            if let Some(curve) = slot.world_slot(sim) {
+                let color = Color::srgb(0.8, 0.8, 0.8);
+                let samples = curve.samples(32);
+                gizmos.linestrip(samples.iter().copied().map(to_bevy), color);
+                // stops
+                let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(slot.axis));
+                for end in [samples[0], samples[samples.len() - 1]] {
+                    gizmos.circle(Isometry3d::new(to_bevy(end), rotation), 0.06, color);
+                }
+            }
+        }
+
+        if let Some(range) = constraint.as_any().downcast_ref::<DistanceRangeConstraint>() {
+            if let (Some(a), Some(b)) = (sim.joints.get(range.joint_a), sim.joints.get(range.joint_b)) {
+                // slack is faint, a pair sitting on either end of its range is drawn solid
+                let distance = a.position.distance(b.position);
+                let at_stop = distance <= range.min_distance + 1e-4 || distance >= range.max_distance - 1e-4;
+                let color = if at_stop { Color::srgb(0.9, 0.8, 0.2) } else { Color::srgba(0.9, 0.8, 0.2, 0.3) };
+                gizmos.line(to_bevy(a.position.as_vec3()), to_bevy(b.position.as_vec3()), color);
+            }
+        }
+
+        if let Some(prismatic) = constraint.as_any().downcast_ref::<PrismaticConstraintVector>() {
+            if let Some((min, max)) = prismatic.limits {
+                // rail between the end stops, open ends are cut short
+                let color = Color::srgb(0.8, 0.8, 0.8);
+                let (start, end) = (prismatic.origin + prismatic.axis * min.max(-10.0), prismatic.origin + prismatic.axis * max.min(10.0));
+                gizmos.line(to_bevy(start), to_bevy(end), color);
+                let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(prismatic.axis));
+                for (stop, limit) in [(start, min), (end, max)] {
+                    if limit.is_finite() {
+                        gizmos.circle(Isometry3d::new(to_bevy(stop), rotation), 0.06, color);
+                    }
+                }
+            }
+        }
+
+        if let Some(cam) = constraint.as_any().downcast_ref::<CamConstraint>() {
+            let color = Color::srgb(0.9, 0.5, 0.1);
+            let outline = cam.world_outline(sim, 128);
+            if let Some(first) = outline.first() {
+                // close the loop
+                gizmos.linestrip(outline.iter().chain(std::iter::once(first)).map(|p| to_bevy(*p)), color);
+            }
+            if cam.roller_radius > 0.0 {
+                if let Some(follower) = sim.joints.get(cam.follower) {
+                    let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(cam.axis));
+                    let centre = to_bevy(follower.position.as_vec3());
+                    gizmos.circle(Isometry3d::new(centre, rotation), cam.roller_radius, color);
+                }
+            }
+        }
+    }
+
+    for element in &sim.forces {
+        if let Some(spring) = element.as_any().downcast_ref::<LinearSpring>() {
+            let (Some(a), Some(b)) = (sim.joints.get(spring.joint_a), sim.joints.get(spring.joint_b)) else {
+                continue;
+            };
+            let (start, end) = (to_bevy(a.position.as_vec3()), to_bevy(b.position.as_vec3()));
+            let Some(along) = (end - start).try_normalize() else {
+                continue;
+            };
+            // zigzag, a damper alone is a plain line
+            let color = Color::srgb(0.4, 0.7, 1.0);
+            if spring.stiffness <= 0.0 {
+                gizmos.line(start, end, color);
+                continue;
+            }
+            let side = along.any_orthonormal_vector() * 0.08;
+            let coils = 12;
+            let points = (0..=coils).map(|k| {
+                let offset = if k == 0 || k == coils { Vec3::ZERO } else if k % 2 == 0 { side } else { -side };
+                start.lerp(end, k as f32 / coils as f32) + offset
+            });
+            gizmos.linestrip(points, color);
+        }
+
+        if let Some(spring) = element.as_any().downcast_ref::<TorsionalSpring>() {
+            if let Some(pivot) = sim.joints.get(spring.pivot) {
+                let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(spring.axis));
+                let centre = to_bevy(pivot.position.as_vec3());
+                gizmos.circle(Isometry3d::new(centre, rotation), 0.15, Color::srgb(0.4, 0.7, 1.0));
+            }
+        }
+
+        // loads as arrows ending on the joint, one length unit per ten newtons
+        if let Some(load) = element.as_any().downcast_ref::<PointLoad>() {
+            if let Some(joint) = sim.joints.get(load.joint_id) {
+                let tip = to_bevy(joint.position.as_vec3());
+                gizmos.arrow(tip - to_bevy(load.force) * 0.1, tip, Color::srgb(1.0, 0.4, 0.1));
+            }
+        }
+
+        if let Some(applied) = element.as_any().downcast_ref::<AppliedTorque>() {
+            if let Some(pivot) = sim.joints.get(applied.pivot) {
+                let rotation = Quat::from_rotation_arc(Vec3::Z, to_bevy(applied.axis));
+                let centre = to_bevy(pivot.position.as_vec3());
+                gizmos.circle(Isometry3d::new(centre, rotation), 0.2, Color::srgb(1.0, 0.4, 0.1));
+            }
+        }
+    }
+}
+
+pub fn play_pause_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    input_focus: Res<InputFocus>,
+    mut wrapper: ResMut<SimWrapper>,
+) {
+    if input_focus.egui_focused {
+        return;
+    }
+    if keys.just_pressed(bindings.play_pause) {
+        wrapper.running = !wrapper.running;
+    }
+}
+
 pub fn sim_step_system(
     mut wrapper: ResMut<SimWrapper>,
     bindings: Res<KeyBindings>,
     move_events: EventReader<MoveJoint>,
-
+    time: Res<Time>,
 
 ) {
-    // Only run simulation step if there were joint movements
-    if !move_events.is_empty() {
-        wrapper.sim.step(0.0, bindings.iterations_per_time_step);
+    if wrapper.running {
+        // Continuous mode, integrate every frame
+        let dt = time.delta_secs().min(MAX_SIM_TIME_STEP);
+        wrapper.last_report = wrapper.sim.step(dt, bindings.iterations_per_time_step, bindings.solver_tolerance);
+    } else if !move_events.is_empty() {
+        // Otherwise only run simulation step if there were joint movements
+        wrapper.last_report = wrapper.sim.step(0.0, bindings.iterations_per_time_step, bindings.solver_tolerance);
     }
 }
 
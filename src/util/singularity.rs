@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use crate::util::constants::*;
+use crate::util::interact::Selected;
+use crate::simcore::analysis::*;
+use crate::simcore::types::*;
+
+#[derive(Event)]
+pub struct SingularityEntered {
+    pub singularity: Singularity,
+}
+
+#[derive(Event)]
+pub struct SingularityLeft {
+    pub kind: SingularityKind,
+}
+
+#[derive(Resource)]
+pub struct SingularityState {
+    pub active: Option<Singularity>,
+    pub tolerance: f64,
+    pub baseline: MobilityBaseline, // start over when a new mechanism is loaded
+    pending: bool, // the pose changed since the last check
+    since_check: f32, // seconds
+}
+
+impl Default for SingularityState {
+    fn default() -> Self {
+        Self {
+            active: None,
+            tolerance: SINGULARITY_TOLERANCE,
+            baseline: MobilityBaseline::default(),
+            pending: false,
+            since_check: 0.0,
+        }
+    }
+}
+
+pub fn singularity_detection_system(
+    sim_wrapper: Res<SimWrapper>,
+    time: Res<Time>,
+    selected_joints: Query<&JointWrapper, With<Selected>>,
+    mut state: ResMut<SingularityState>,
+    mut entered_events: EventWriter<SingularityEntered>,
+    mut left_events: EventWriter<SingularityLeft>,
+) {
+    // the eigen decomposition is too slow to redo every frame
+    state.pending |= sim_wrapper.is_changed();
+    state.since_check += time.delta_secs();
+    if !state.pending || state.since_check < SINGULARITY_CHECK_INTERVAL {
+        return;
+    }
+    state.pending = false;
+    state.since_check = 0.0;
+
+    // Dragged and driven joints are inputs, their motion is imposed
+    let mut inputs: Vec<JointId> = selected_joints.iter().map(|j| j.joint_id).collect();
+    for constraint in &sim_wrapper.sim.constraints {
+        if let Some(driver) = constraint.as_any().downcast_ref::<CrankDriverConstraint>() {
+            inputs.push(driver.moving_joint_id);
+        }
+        if let Some(actuator) = constraint.as_any().downcast_ref::<LinearActuatorConstraint>() {
+            inputs.push(actuator.joint_id);
+        }
+        if let Some(angle) = constraint.as_any().downcast_ref::<FixedAngleConstraint>() {
+            if angle.drive.is_some() {
+                inputs.push(angle.joint_b_id);
+            }
+        }
+    }
+    let SingularityState { tolerance, baseline, .. } = &mut *state;
+    let found = sim_wrapper.sim.singularity(&inputs, *tolerance, baseline);
+
+    let previous_kind = state.active.as_ref().map(|s| s.kind);
+    let found_kind = found.as_ref().map(|s| s.kind);
+    if previous_kind != found_kind {
+        if let Some(kind) = previous_kind {
+            left_events.write(SingularityLeft { kind });
+        }
+        if let Some(singularity) = &found {
+            entered_events.write(SingularityEntered { singularity: singularity.clone() });
+        }
+    }
+    state.active = found;
+}
+
+pub fn singularity_log_system(
+    sim_wrapper: Res<SimWrapper>,
+    mut entered_events: EventReader<SingularityEntered>,
+    mut left_events: EventReader<SingularityLeft>,
+) {
+    for event in entered_events.read() {
+        let names: Vec<&str> = event.singularity.joints.iter()
+            .filter_map(|id| sim_wrapper.sim.joints.get(*id).map(|j| j.name.as_str()))
+            .collect();
+        info!(
+            "{:?} at t = {:.3}s, joints [{}], measure {:.4}",
+            event.singularity.kind, sim_wrapper.sim.time, names.join(", "), event.singularity.measure
+        );
+    }
+    for event in left_events.read() {
+        info!("left {:?} at t = {:.3}s", event.kind, sim_wrapper.sim.time);
+    }
+}
+
+pub fn singularity_gizmo_system(
+    sim_wrapper: Res<SimWrapper>,
+    state: Res<SingularityState>,
+    mut gizmos: Gizmos,
+) {
+    let Some(singularity) = &state.active else {
+        return;
+    };
+    let sim = &sim_wrapper.sim;
+    let color = match singularity.kind {
+        SingularityKind::ChangePoint => Color::srgb(1.0, 0.0, 0.0),
+        SingularityKind::DeadPoint => Color::srgb(1.0, 0.0, 1.0),
+        SingularityKind::Toggle => Color::srgb(1.0, 0.5, 0.0),
+    };
+
+    for joint_id in &singularity.joints {
+        if let Some(joint) = sim.joints.get(*joint_id) {
+            let pos = joint.position.as_vec3();
+            gizmos.sphere(Isometry3d::from_translation(Vec3::new(pos.x, pos.y, pos.z)), 0.2, color);
+        }
+    }
+
+    // Trace the links that line up
+    for index in &singularity.constraints {
+        let Some(constraint) = sim.constraints.get(*index) else {
+            continue;
+        };
+        if let Some(distance) = constraint.as_any().downcast_ref::<DistanceConstraint>() {
+            if let (Some(a), Some(b)) = (sim.joints.get(distance.joint_a), sim.joints.get(distance.joint_b)) {
+                let start = a.position.as_vec3();
+                let end = b.position.as_vec3();
+                gizmos.line(Vec3::new(start.x, start.y, start.z), Vec3::new(end.x, end.y, end.z), color);
+            }
+        }
+    }
+}
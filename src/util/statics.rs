@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use crate::util::constants::*;
+use crate::simcore::statics::*;
+
+#[derive(Resource)]
+pub struct StaticsState {
+    pub show: bool,
+    pub report: StaticReport,
+    pub arrow_scale: f32, // length units per unit of force
+}
+
+impl Default for StaticsState {
+    fn default() -> Self {
+        Self {
+            show: false,
+            report: StaticReport::default(),
+            arrow_scale: 0.1,
+        }
+    }
+}
+
+pub fn statics_system(
+    sim_wrapper: Res<SimWrapper>,
+    mut state: ResMut<StaticsState>,
+) {
+    if !state.show {
+        return;
+    }
+    state.report = sim_wrapper.sim.static_forces();
+}
+
+pub fn statics_gizmo_system(
+    sim_wrapper: Res<SimWrapper>,
+    state: Res<StaticsState>,
+    mut gizmos: Gizmos,
+) {
+    if !state.show {
+        return;
+    }
+    let sim = &sim_wrapper.sim;
+    let to_bevy = |v: glam::Vec3| Vec3::new(v.x, v.y, v.z);
+
+    // support reactions push on the joint from outside
+    for reaction in &state.report.reactions {
+        let Some(joint) = sim.joints.get(reaction.joint_id) else {
+            continue;
+        };
+        if reaction.support.length() < 1e-4 {
+            continue;
+        }
+        let tip = to_bevy(joint.position.as_vec3());
+        gizmos.arrow(tip - to_bevy(reaction.support) * state.arrow_scale, tip, Color::srgb(0.2, 0.9, 0.3));
+    }
+
+    // links in tension blue, in compression red
+    for (link_id, tension) in &state.report.link_loads {
+        let Some(link) = sim.links.get(*link_id) else {
+            continue;
+        };
+        let (Some(a), Some(b)) = (sim.joints.get(link.joints[0]), sim.joints.get(link.joints[1])) else {
+            continue;
+        };
+        let color = if *tension >= 0.0 { Color::srgb(0.3, 0.5, 1.0) } else { Color::srgb(1.0, 0.3, 0.3) };
+        gizmos.line(to_bevy(a.position.as_vec3()), to_bevy(b.position.as_vec3()), color);
+    }
+}